@@ -1,87 +1,292 @@
 extern crate ffmpeg_next as ffmpeg;
 
-use std::time::Duration;
+use std::path::PathBuf;
+use std::process::ExitCode;
 
 use ffmpeg::format::{input, Pixel};
 use ffmpeg::media::Type;
 use ffmpeg::software::scaling::{context::Context, flag::Flags};
-use ffmpeg::time::sleep;
 use ffmpeg::util::frame::video::Video;
-use virtualcam_rs::Camera;
+use virtualcam_rs::file_sink::FileSink;
+use virtualcam_rs::format::PixelFormat;
+use virtualcam_rs::mock::MockBackend;
+use virtualcam_rs::pacer::{now_100ns, FrameRate, Pacer, TICKS_PER_SECOND};
+use virtualcam_rs::scale::{scale, FitMode};
+use virtualcam_rs::{Backend, Camera, Frame};
+
+const USAGE: &str = "\
+usage: mp4_to_vcam INPUT [options]
+
+options:
+  --size WxH        camera size (default: 1920x1080)
+  --start N         first frame to play (default: 0)
+  --end N           stop before frame N (default: end of file)
+  --loop            start over at the end
+  --backend NAME    camera backend: unity (default), obs, file to write
+                    --output, or mock to record frames in memory
+  --output PATH     file for the file backend: .y4m, or a raw dump with a
+                    PATH.json sidecar
+  --device NAME     device name (default: Unity Video Capture)
+";
+
+/// Used when the input does not declare a frame rate.
+const DEFAULT_RATE: FrameRate = FrameRate::from_integer(25);
 
 #[derive(Debug)]
-struct Error;
+enum Error {
+    Usage(String),
+    Vcam(virtualcam_rs::Error),
+    Ffmpeg(ffmpeg::Error),
+}
 
 impl From<virtualcam_rs::Error> for Error {
     fn from(e: virtualcam_rs::Error) -> Self {
-        e.into()
+        Error::Vcam(e)
     }
 }
 
 impl From<ffmpeg::Error> for Error {
     fn from(e: ffmpeg::Error) -> Self {
-        e.into()
+        Error::Ffmpeg(e)
     }
 }
 
-fn main() -> Result<(), Error> {
-    let mut vcam = Camera::new(1920, 1080, "Unity Video Capture")?;
+struct Args {
+    input: String,
+    width: u32,
+    height: u32,
+    start: usize,
+    end: Option<usize>,
+    looping: bool,
+    backend: String,
+    output: Option<PathBuf>,
+    device: String,
+}
 
-    ffmpeg::init().unwrap();
-    loop {
-        let start_frame: usize = 100;
-        let end_frame: usize = 200;
-        if let Ok(mut ictx) = input(&"C:/Users/Administrator/Desktop/CLHL/model/视频模型/003/012")
-        {
-            ictx.seek(
-                (end_frame - start_frame) as i64,
-                start_frame as i64..end_frame as i64,
-            )?;
+fn parse_args() -> Result<Args, Error> {
+    let usage = |message: String| Error::Usage(format!("{}\n\n{}", message, USAGE));
+    let mut args = std::env::args().skip(1);
+    let mut parsed = Args {
+        input: String::new(),
+        width: 1920,
+        height: 1080,
+        start: 0,
+        end: None,
+        looping: false,
+        backend: "unity".to_owned(),
+        output: None,
+        device: "Unity Video Capture".to_owned(),
+    };
+    while let Some(arg) = args.next() {
+        if arg == "--loop" {
+            parsed.looping = true;
+            continue;
+        }
+        if !arg.starts_with("--") {
+            parsed.input = arg;
+            continue;
+        }
+        let value = args
+            .next()
+            .ok_or_else(|| usage(format!("missing value for {}", arg)))?;
+        let invalid = || usage(format!("invalid value for {}: {}", arg, value));
+        match arg.as_str() {
+            "--size" => {
+                let (w, h) = value.split_once('x').ok_or_else(invalid)?;
+                parsed.width = w.parse().map_err(|_| invalid())?;
+                parsed.height = h.parse().map_err(|_| invalid())?;
+            }
+            "--start" => parsed.start = value.parse().map_err(|_| invalid())?,
+            "--end" => parsed.end = Some(value.parse().map_err(|_| invalid())?),
+            "--backend" if ["unity", "obs", "file", "mock"].contains(&value.as_str()) => {
+                parsed.backend = value
+            }
+            "--output" => parsed.output = Some(PathBuf::from(value)),
+            "--device" => parsed.device = value,
+            _ => return Err(invalid()),
+        }
+    }
+    if parsed.input.is_empty() {
+        return Err(usage("missing INPUT".to_owned()));
+    }
+    Ok(parsed)
+}
+
+/// Largest size with the source's aspect ratio that fits the camera.
+fn fitted_size(src: (u32, u32), dst: (u32, u32)) -> (u32, u32) {
+    let scale = (dst.0 as f64 / src.0 as f64).min(dst.1 as f64 / src.1 as f64);
+    let w = ((src.0 as f64 * scale).round() as u32).clamp(1, dst.0);
+    let h = ((src.1 as f64 * scale).round() as u32).clamp(1, dst.1);
+    (w, h)
+}
+
+/// Plays the selected range once. Timestamps continue from `offset`, and
+/// the offset for the next pass is returned.
+fn play(args: &Args, vcam: &mut Camera, offset: u64) -> Result<u64, Error> {
+    let mut ictx = input(&args.input)?;
+    let input = ictx
+        .streams()
+        .best(Type::Video)
+        .ok_or(ffmpeg::Error::StreamNotFound)?;
+    let video_stream_index = input.index();
+    let time_base = input.time_base();
+    let interval = frame_rate(input.avg_frame_rate())
+        .unwrap_or(DEFAULT_RATE)
+        .interval();
+    vcam.set_pacer(Some(Pacer::new(interval)));
+    // pts in stream time base to 100 ns units
+    let to_ticks = |pts: i64| {
+        (pts.max(0) as i128 * time_base.numerator() as i128 * TICKS_PER_SECOND as i128
+            / time_base.denominator().max(1) as i128) as u64
+    };
+
+    let context_decoder = ffmpeg::codec::context::Context::from_parameters(input.parameters())?;
+    let mut decoder = context_decoder.decoder().video()?;
+    let (fit_w, fit_h) = fitted_size(
+        (decoder.width(), decoder.height()),
+        (args.width, args.height),
+    );
+    let mut scaler = Context::get(
+        decoder.format(),
+        decoder.width(),
+        decoder.height(),
+        Pixel::RGBA,
+        fit_w,
+        fit_h,
+        Flags::BILINEAR,
+    )?;
+
+    let mut index = 0;
+    let mut first_pts = None;
+    let mut last_ts = offset;
+    let mut decoded = Video::empty();
+    let mut rgb_frame = Video::empty();
+    let mut packed = vec![0; fit_w as usize * fit_h as usize * 4];
+    // OBS takes NV12, the other backends RGBA
+    let format = vcam.format();
+    let mut receive = |decoder: &mut ffmpeg::decoder::Video| -> Result<bool, Error> {
+        while decoder.receive_frame(&mut decoded).is_ok() {
+            index += 1;
+            if index <= args.start {
+                continue;
+            }
+            if args.end.is_some_and(|end| index > end) {
+                return Ok(false);
+            }
+            let pts = decoded.timestamp().map(to_ticks).unwrap_or(0);
+            let first = *first_pts.get_or_insert(pts);
+            let timestamp = offset + pts.saturating_sub(first);
+            last_ts = timestamp;
 
-            let input = ictx
-                .streams()
-                .best(Type::Video)
-                .ok_or(ffmpeg::Error::StreamNotFound)?;
-
-            let video_stream_index = input.index();
-
-            let context_decoder =
-                ffmpeg::codec::context::Context::from_parameters(input.parameters())?;
-            let mut decoder = context_decoder.decoder().video()?;
-
-            let mut scaler = Context::get(
-                decoder.format(),
-                decoder.width(),
-                decoder.height(),
-                Pixel::RGBA,
-                1920,
-                1080,
-                Flags::BILINEAR,
+            scaler.run(&decoded, &mut rgb_frame)?;
+            // rows may be padded past the visible width
+            let stride = rgb_frame.stride(0);
+            let row = fit_w as usize * 4;
+            for (y, dst) in packed.chunks_exact_mut(row).enumerate() {
+                dst.copy_from_slice(&rgb_frame.data(0)[y * stride..][..row]);
+            }
+            let data = scale(
+                &packed,
+                PixelFormat::Rgba,
+                fit_w,
+                fit_h,
+                format,
+                args.width,
+                args.height,
+                FitMode::Fit,
             )?;
+            match vcam.send(Frame::with_timestamp(data, timestamp)) {
+                // the consumer or the pacer passing on a frame is routine
+                Ok(())
+                | Err(virtualcam_rs::Error::SendresWarnFrameskip)
+                | Err(virtualcam_rs::Error::FrameDropped) => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+        Ok(true)
+    };
 
-            'outer: for (stream, packet) in ictx.packets() {
-                if stream.index() == video_stream_index {
-                    decoder.send_packet(&packet)?;
-                    let mut decoded = Video::empty();
-                    while decoder.receive_frame(&mut decoded).is_ok() {
-                        let mut rgb_frame = Video::empty();
-                        if decoded.coded_number() >= end_frame {
-                            break 'outer;
-                        }
-                        scaler.run(&decoded, &mut rgb_frame)?;
-                        let rgb_u8 = rgb_frame.data(0);
-                        match vcam.send(rgb_u8.to_vec()) {
-                            Ok(_) => {}
-                            Err(e) => {
-                                println!("send error: {:?}", e);
-                            }
-                        };
-
-                        sleep(25000)?;
-                    }
-                }
+    'packets: for (stream, packet) in ictx.packets() {
+        if stream.index() == video_stream_index {
+            decoder.send_packet(&packet)?;
+            if !receive(&mut decoder)? {
+                break 'packets;
             }
-            decoder.send_eof()?;
+        }
+    }
+    decoder.send_eof()?;
+    receive(&mut decoder)?;
+    Ok(last_ts + interval)
+}
+
+/// `rate` as a [`FrameRate`], if it is a valid one; ffmpeg reports 0/0 for
+/// streams that do not declare a rate.
+fn frame_rate(rate: ffmpeg::Rational) -> Option<FrameRate> {
+    let num = u32::try_from(rate.numerator()).ok()?;
+    let den = u32::try_from(rate.denominator()).ok()?;
+    FrameRate::new(num, den).ok()
+}
+
+/// The input's average frame rate, if it declares one.
+fn input_rate(path: &str) -> Result<Option<FrameRate>, Error> {
+    let ictx = input(path)?;
+    let stream = ictx
+        .streams()
+        .best(Type::Video)
+        .ok_or(ffmpeg::Error::StreamNotFound)?;
+    Ok(frame_rate(stream.avg_frame_rate()))
+}
+
+fn open_camera(args: &Args) -> Result<Camera, Error> {
+    let (width, height) = (args.width, args.height);
+    let camera = match args.backend.as_str() {
+        "obs" => {
+            let rate = input_rate(&args.input)?.unwrap_or(DEFAULT_RATE);
+            Camera::obs(width, height, rate)?
+        }
+        "file" => {
+            let path = args
+                .output
+                .as_ref()
+                .ok_or_else(|| Error::Usage(format!("file backend needs --output\n\n{}", USAGE)))?;
+            let rate = input_rate(&args.input)?.unwrap_or(DEFAULT_RATE);
+            let sink = FileSink::create(path, width, height, PixelFormat::Rgba)?.frame_rate(rate);
+            Camera::from_backend(Backend::FileSink(sink))
+        }
+        "mock" => Camera::from_backend(Backend::Mock(MockBackend::new(
+            width,
+            height,
+            PixelFormat::Rgba,
+        ))),
+        _ => Camera::new(width as i32, height as i32, &args.device)?,
+    };
+    Ok(camera)
+}
+
+fn run() -> Result<(), Error> {
+    let args = parse_args()?;
+    ffmpeg::init()?;
+    let mut vcam = open_camera(&args)?;
+
+    let mut offset = now_100ns();
+    loop {
+        offset = play(&args, &mut vcam, offset)?;
+        if !args.looping {
+            return Ok(());
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    match run() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(Error::Usage(message)) => {
+            eprintln!("{}", message);
+            ExitCode::FAILURE
+        }
+        Err(e) => {
+            eprintln!("error: {:?}", e);
+            ExitCode::FAILURE
         }
     }
 }
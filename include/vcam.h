@@ -0,0 +1,114 @@
+#ifndef VCAM_H
+#define VCAM_H
+
+/* Generated by cbindgen from src/capi.rs; do not edit. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/**
+ * Result of every fallible `vcam_*` call.
+ */
+typedef enum VcamStatus {
+  VCAM_STATUS_OK = 0,
+  VCAM_STATUS_INVALID_ARGUMENT = 1,
+  VCAM_STATUS_DEVICE_NOT_FOUND = 2,
+  VCAM_STATUS_NOT_READY = 3,
+  VCAM_STATUS_FRAME_TOO_LARGE = 4,
+  VCAM_STATUS_FRAME_SKIPPED = 5,
+  VCAM_STATUS_FRAME_DROPPED = 6,
+  VCAM_STATUS_TIMEOUT = 7,
+  VCAM_STATUS_UNSUPPORTED = 8,
+  VCAM_STATUS_IO = 9,
+  VCAM_STATUS_UNKNOWN = 10,
+  VCAM_STATUS_IN_USE = 11,
+  VCAM_STATUS_INVALID_FRAME_SIZE = 12,
+} VcamStatus;
+
+/**
+ * Opaque camera handle.
+ */
+typedef struct VcamCamera VcamCamera;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * Opens `device` on `backend` at `width`×`height` and stores the handle in
+ * `*out`. Backends are `"unity"` (RGBA), `"obs"` (NV12 at 30 fps, `device`
+ * is ignored) and `"mock"`, an in-memory RGBA camera that accepts every
+ * frame. A null `device` picks the default device.
+ *
+ * # Safety
+ * `backend` and `device` must be null or NUL-terminated strings, and `out`
+ * must be valid for a pointer write.
+ */
+enum VcamStatus vcam_open(const char *backend,
+                          const char *device,
+                          uint32_t width,
+                          uint32_t height,
+                          struct VcamCamera **out);
+
+/**
+ * Sends `len` bytes in the camera's pixel format. A `timestamp` of 0 stamps
+ * the frame with the current time (100 ns units). A frame that is not
+ * exactly the camera's frame size is `VCAM_STATUS_INVALID_FRAME_SIZE`.
+ *
+ * # Safety
+ * `camera` must come from `vcam_open`, and `data` must be valid for `len`
+ * bytes.
+ */
+enum VcamStatus vcam_send(struct VcamCamera *camera,
+                          const uint8_t *data,
+                          size_t len,
+                          uint64_t timestamp);
+
+/**
+ * Sets a named option:
+ * - `fps`: pace frames at this rate, `0` to send immediately
+ * - `lock_timeout_ms`: UnityCapture mutex timeout, `-1` to wait forever
+ *
+ * # Safety
+ * `camera` must come from `vcam_open`; `name` and `value` must be
+ * NUL-terminated strings.
+ */
+enum VcamStatus vcam_set_option(struct VcamCamera *camera, const char *name, const char *value);
+
+/**
+ * Writes the registered device names, one per line and NUL-terminated, into
+ * `buf` if it holds `len` bytes or more. Returns the size needed, including
+ * the terminator, so callers can size `buf` with a first call passing null.
+ *
+ * # Safety
+ * `buf` must be null or valid for `len` bytes.
+ */
+size_t vcam_list_devices(char *buf, size_t len);
+
+/**
+ * Closes a camera. Null is ignored.
+ *
+ * # Safety
+ * `camera` must come from `vcam_open` and not be used afterwards.
+ */
+void vcam_close(struct VcamCamera *camera);
+
+/**
+ * Static description of a status code.
+ */
+const char *vcam_status_string(int32_t status);
+
+/**
+ * Details of the last error on this thread, valid until the next failing
+ * call on the same thread.
+ */
+const char *vcam_last_error(void);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* VCAM_H */
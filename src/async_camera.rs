@@ -0,0 +1,229 @@
+use std::future::{poll_fn, Future};
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+use std::time::Duration;
+
+use futures_sink::Sink;
+use tokio::task::{spawn_blocking, JoinHandle};
+
+use crate::pacer::now_100ns;
+use crate::{Camera, Error, Frame};
+
+/// A blocking operation yields `Ok(true)` on success, or the answer to a
+/// query such as `is_ready`.
+type InFlight = JoinHandle<(Camera, Result<bool, Error>)>;
+
+/// A [`Camera`] driven from async code.
+///
+/// Every blocking backend call runs on Tokio's blocking pool. The camera
+/// moves into the blocking task and back, so operations are serialized and
+/// cancelling a future never loses the camera: the next call waits for the
+/// abandoned operation to finish first.
+pub struct AsyncCamera {
+    camera: Option<Camera>,
+    in_flight: Option<InFlight>,
+}
+
+impl AsyncCamera {
+    pub fn new(camera: Camera) -> Self {
+        Self {
+            camera: Some(camera),
+            in_flight: None,
+        }
+    }
+
+    pub async fn send<F: Into<Frame>>(&mut self, frame: F) -> Result<(), Error> {
+        let mut frame = frame.into();
+        frame.timestamp.get_or_insert_with(now_100ns);
+        self.run(move |camera| camera.send(frame).map(|()| true))
+            .await
+            .map(drop)
+    }
+
+    /// Whether the consumer side is up, see [`Camera::is_ready`].
+    pub async fn is_ready(&mut self) -> Result<bool, Error> {
+        self.run(|camera| Ok(camera.is_ready())).await
+    }
+
+    /// Resolves once a consumer has connected, checking every `poll_interval`.
+    pub async fn wait_ready(&mut self, poll_interval: Duration) -> Result<(), Error> {
+        while !self.is_ready().await? {
+            tokio::time::sleep(poll_interval).await;
+        }
+        Ok(())
+    }
+
+    /// Waits for any pending operation and returns the camera.
+    pub async fn into_inner(mut self) -> Result<Camera, Error> {
+        poll_fn(|cx| self.poll_idle(cx)).await.ok();
+        self.camera.take().ok_or(Error::WorkerStopped)
+    }
+
+    async fn run<T>(&mut self, f: T) -> Result<bool, Error>
+    where
+        T: FnOnce(&mut Camera) -> Result<bool, Error> + Send + 'static,
+    {
+        // A result left over from a cancelled call belongs to that call.
+        poll_fn(|cx| self.poll_idle(cx)).await.ok();
+        self.start(f)?;
+        poll_fn(|cx| self.poll_idle(cx)).await
+    }
+
+    fn start<T>(&mut self, f: T) -> Result<(), Error>
+    where
+        T: FnOnce(&mut Camera) -> Result<bool, Error> + Send + 'static,
+    {
+        let mut camera = self.camera.take().ok_or(Error::WorkerStopped)?;
+        self.in_flight = Some(spawn_blocking(move || {
+            let result = f(&mut camera);
+            (camera, result)
+        }));
+        Ok(())
+    }
+
+    /// Completes the pending operation, if any, and yields its result.
+    fn poll_idle(&mut self, cx: &mut Context<'_>) -> Poll<Result<bool, Error>> {
+        let Some(in_flight) = &mut self.in_flight else {
+            return Poll::Ready(Ok(true));
+        };
+        let joined = ready!(Pin::new(in_flight).poll(cx));
+        self.in_flight = None;
+        match joined {
+            Ok((camera, result)) => {
+                self.camera = Some(camera);
+                Poll::Ready(result)
+            }
+            // The backend panicked and took the camera with it.
+            Err(_) => Poll::Ready(Err(Error::WorkerStopped)),
+        }
+    }
+
+    /// Like `poll_idle`, but treats per-frame warnings as success so a
+    /// skipped or late frame does not end a forwarded stream.
+    fn poll_sink_idle(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Error>> {
+        match ready!(self.poll_idle(cx)) {
+            Ok(_) | Err(Error::SendresWarnFrameskip) | Err(Error::FrameDropped) => {
+                Poll::Ready(Ok(()))
+            }
+            Err(e) => Poll::Ready(Err(e)),
+        }
+    }
+}
+
+impl<F: Into<Frame>> Sink<F> for AsyncCamera {
+    type Error = Error;
+
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Error>> {
+        self.get_mut().poll_sink_idle(cx)
+    }
+
+    fn start_send(self: Pin<&mut Self>, item: F) -> Result<(), Error> {
+        let mut frame = item.into();
+        frame.timestamp.get_or_insert_with(now_100ns);
+        self.get_mut()
+            .start(move |camera| camera.send(frame).map(|()| true))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Error>> {
+        self.get_mut().poll_sink_idle(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Error>> {
+        self.get_mut().poll_sink_idle(cx)
+    }
+}
+
+#[cfg(test)]
+fn mock_camera(mock: &crate::mock::MockBackend) -> AsyncCamera {
+    AsyncCamera::new(Camera::from_backend(crate::Backend::Mock(mock.clone())))
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_async_camera_send() {
+    use crate::format::PixelFormat;
+    use crate::mock::{MockAction, MockBackend};
+
+    let mock = MockBackend::new(2, 1, PixelFormat::Rgba).on_send(1, MockAction::Skip);
+    let mut camera = mock_camera(&mock);
+    camera.send(vec![1; 8]).await.unwrap();
+    assert!(matches!(
+        camera.send(Frame::with_timestamp(vec![2; 8], 7)).await,
+        Err(Error::SendresWarnFrameskip)
+    ));
+    assert!(camera.is_ready().await.unwrap());
+    assert_eq!(camera.into_inner().await.unwrap().stats().sent, 1);
+    assert_eq!(mock.frames()[1].timestamp, 7);
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_async_camera_sink() {
+    use crate::format::PixelFormat;
+    use crate::mock::{MockAction, MockBackend};
+
+    let mock = MockBackend::new(2, 1, PixelFormat::Rgba).on_send(0, MockAction::Skip);
+    let mut camera = mock_camera(&mock);
+    for i in 0..3u8 {
+        poll_fn(|cx| Sink::<Vec<u8>>::poll_ready(Pin::new(&mut camera), cx))
+            .await
+            .unwrap();
+        Pin::new(&mut camera).start_send(vec![i; 8]).unwrap();
+    }
+    poll_fn(|cx| Sink::<Vec<u8>>::poll_flush(Pin::new(&mut camera), cx))
+        .await
+        .unwrap();
+    // a real error still ends the stream
+    Pin::new(&mut camera).start_send(vec![0; 3]).unwrap();
+    assert!(matches!(
+        poll_fn(|cx| Sink::<Vec<u8>>::poll_close(Pin::new(&mut camera), cx)).await,
+        Err(Error::InvalidFrameSize)
+    ));
+    assert_eq!(mock.frames().len(), 3);
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_async_camera_wait_ready() {
+    use crate::format::PixelFormat;
+    use crate::mock::MockBackend;
+
+    let mock = MockBackend::new(2, 1, PixelFormat::Rgba);
+    mock.set_connected(false);
+    let mut camera = mock_camera(&mock);
+    assert!(!camera.is_ready().await.unwrap());
+    let consumer = mock.clone();
+    tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        consumer.set_connected(true);
+    });
+    tokio::time::timeout(
+        Duration::from_secs(5),
+        camera.wait_ready(Duration::from_millis(1)),
+    )
+    .await
+    .unwrap()
+    .unwrap();
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_async_camera_panic() {
+    use crate::format::PixelFormat;
+    use crate::mock::{MockAction, MockBackend};
+
+    let mock = MockBackend::new(2, 1, PixelFormat::Rgba).on_send(0, MockAction::Panic);
+    let mut camera = mock_camera(&mock);
+    assert!(matches!(
+        camera.send(vec![0; 8]).await,
+        Err(Error::WorkerStopped)
+    ));
+    assert!(matches!(
+        camera.send(vec![0; 8]).await,
+        Err(Error::WorkerStopped)
+    ));
+    assert!(matches!(
+        camera.into_inner().await,
+        Err(Error::WorkerStopped)
+    ));
+}
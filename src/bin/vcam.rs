@@ -0,0 +1,257 @@
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use virtualcam_rs::file_sink::FileSink;
+use virtualcam_rs::format::PixelFormat;
+use virtualcam_rs::mock::MockBackend;
+#[cfg(target_os = "windows")]
+use virtualcam_rs::obs_vcam::QueueState;
+use virtualcam_rs::pacer::FrameRate;
+use virtualcam_rs::patterns::{self, Pattern};
+use virtualcam_rs::scale::FitMode;
+use virtualcam_rs::source::{self, RawSource, VideoSource, Y4mSource};
+#[cfg(target_os = "windows")]
+use virtualcam_rs::unity_capture;
+use virtualcam_rs::{Backend, Camera, Error};
+
+const USAGE: &str = "\
+usage: vcam <command> [options]
+
+commands:
+  list                      list registered virtual camera devices
+  probe                     report whether each device has a consumer attached,
+                            or with --backend obs whether the OBS queue is
+                            free, in use, or releasing (a consumer still has
+                            the stopped queue open)
+  pattern [NAME]            stream a test pattern: bars, checkerboard, gradient,
+                            counter, solid[:RRGGBB] (default: bars)
+  image PATH                stream a PNG/JPEG/BMP/PPM file, reloading on change
+  play PATH|-               play a y4m stream, or raw frames with --format
+  install DLL               register the UnityCapture filter DLL (elevated)
+  uninstall DLL             unregister the UnityCapture filter DLL (elevated)
+
+options:
+  --backend NAME            camera backend: unity (default), obs, file to
+                            write --output, or mock to record frames in memory
+  --output PATH             file for the file backend: .y4m, or a raw dump
+                            with a PATH.json sidecar
+  --device NAME             device name (default: Unity Video Capture)
+  --size WxH                camera size (default: 1280x720)
+  --fps RATE                frame rate such as 30 or 30000/1001 (default: 30,
+                            or the stream's for play)
+  --format FMT              raw input format for play: rgba, bgra, rgb, bgr,
+                            nv12, i420
+  --input-size WxH          raw input size for play (default: --size)
+  --fit MODE                fit, fill or stretch (default: fit)
+  --frames N                stop a pattern after N frames
+  --devices N               number of devices to register on install
+";
+
+const DEFAULT_DEVICE: &str = "Unity Video Capture";
+const DEFAULT_RATE: FrameRate = FrameRate::from_integer(30);
+
+struct Options {
+    command: String,
+    args: Vec<String>,
+    backend: String,
+    device: Option<String>,
+    output: Option<PathBuf>,
+    size: (u32, u32),
+    fps: Option<FrameRate>,
+    format: Option<PixelFormat>,
+    input_size: Option<(u32, u32)>,
+    fit: FitMode,
+    frames: Option<u64>,
+    devices: Option<u32>,
+}
+
+fn parse_size(s: &str) -> Result<(u32, u32), Error> {
+    let (w, h) = s.split_once(['x', 'X']).ok_or(Error::InvalidArgument)?;
+    let w = w.parse().map_err(|_| Error::InvalidArgument)?;
+    let h = h.parse().map_err(|_| Error::InvalidArgument)?;
+    Ok((w, h))
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Options, String> {
+    let command = args.next().ok_or_else(|| USAGE.to_owned())?;
+    let mut options = Options {
+        command,
+        args: Vec::new(),
+        backend: "unity".to_owned(),
+        device: None,
+        output: None,
+        size: (1280, 720),
+        fps: None,
+        format: None,
+        input_size: None,
+        fit: FitMode::Fit,
+        frames: None,
+        devices: None,
+    };
+    while let Some(arg) = args.next() {
+        if !arg.starts_with("--") {
+            options.args.push(arg);
+            continue;
+        }
+        let value = args
+            .next()
+            .ok_or_else(|| format!("missing value for {}", arg))?;
+        let invalid = |_| format!("invalid value for {}: {}", arg, value);
+        match arg.as_str() {
+            "--backend" => options.backend = value,
+            "--device" => options.device = Some(value),
+            "--output" => options.output = Some(PathBuf::from(value)),
+            "--size" => options.size = parse_size(&value).map_err(invalid)?,
+            "--fps" => options.fps = Some(value.parse().map_err(invalid)?),
+            "--format" => options.format = Some(value.parse().map_err(invalid)?),
+            "--input-size" => options.input_size = Some(parse_size(&value).map_err(invalid)?),
+            "--fit" => options.fit = value.parse().map_err(invalid)?,
+            "--frames" => {
+                options.frames = Some(value.parse().map_err(|_| invalid(Error::InvalidArgument))?)
+            }
+            "--devices" => {
+                options.devices = Some(value.parse().map_err(|_| invalid(Error::InvalidArgument))?)
+            }
+            _ => return Err(format!("unknown option {}\n\n{}", arg, USAGE)),
+        }
+    }
+    Ok(options)
+}
+
+/// Opens the selected backend; `rate` is what the obs and file backends
+/// advertise to consumers.
+fn open_camera(options: &Options, rate: FrameRate) -> Result<Camera, Error> {
+    let device = options.device.as_deref().unwrap_or(DEFAULT_DEVICE);
+    let (width, height) = options.size;
+    match options.backend.as_str() {
+        "unity" => Camera::new(width as i32, height as i32, device),
+        "obs" => Camera::obs(width, height, rate),
+        "mock" => Ok(Camera::from_backend(Backend::Mock(MockBackend::new(
+            width,
+            height,
+            PixelFormat::Rgba,
+        )))),
+        "file" => {
+            let path = options.output.as_ref().ok_or(Error::InvalidArgument)?;
+            let sink = FileSink::create(path, width, height, PixelFormat::Rgba)?.frame_rate(rate);
+            Ok(Camera::from_backend(Backend::FileSink(sink)))
+        }
+        _ => Err(Error::InvalidArgument),
+    }
+}
+
+#[cfg(target_os = "windows")]
+fn list_devices() -> Vec<(u32, String)> {
+    unity_capture::list_devices()
+}
+
+#[cfg(not(target_os = "windows"))]
+fn list_devices() -> Vec<(u32, String)> {
+    Vec::new()
+}
+
+fn first_arg(options: &Options, what: &str) -> Result<String, String> {
+    options
+        .args
+        .first()
+        .cloned()
+        .ok_or_else(|| format!("{} requires {}\n\n{}", options.command, what, USAGE))
+}
+
+fn run(options: Options) -> Result<(), String> {
+    let fail = |e: Error| format!("{}: {:?}", options.command, e);
+    match options.command.as_str() {
+        "list" => {
+            for (num, name) in list_devices() {
+                println!("{}\t{}", num, name);
+            }
+        }
+        #[cfg(target_os = "windows")]
+        "probe" if options.backend == "obs" => {
+            let status = match virtualcam_rs::obs_vcam::probe() {
+                Some(QueueState::Stopping) => "releasing".to_owned(),
+                Some(state) => format!("in use ({:?})", state),
+                None => "free".to_owned(),
+            };
+            println!("OBSVirtualCamVideo\t{}", status);
+        }
+        "probe" => {
+            let devices = match &options.device {
+                Some(device) => vec![device.clone()],
+                None => list_devices().into_iter().map(|(_, name)| name).collect(),
+            };
+            for device in devices {
+                let (width, height) = options.size;
+                let status = match Camera::new(width as i32, height as i32, &device) {
+                    Ok(mut camera) => match camera.is_ready() {
+                        true => "ready".to_owned(),
+                        false => "no consumer".to_owned(),
+                    },
+                    Err(e) => format!("{:?}", e),
+                };
+                println!("{}\t{}", device, status);
+            }
+        }
+        "pattern" => {
+            let pattern = match options.args.first() {
+                Some(name) => name.parse().map_err(fail)?,
+                None => Pattern::ColorBars,
+            };
+            let rate = options.fps.unwrap_or(DEFAULT_RATE);
+            let mut camera = open_camera(&options, rate).map_err(fail)?;
+            patterns::stream(&mut camera, pattern, rate, options.frames).map_err(fail)?;
+        }
+        #[cfg(feature = "image")]
+        "image" => {
+            let path = first_arg(&options, "an image path")?;
+            let rate = options.fps.unwrap_or(DEFAULT_RATE);
+            let mut camera = open_camera(&options, rate).map_err(fail)?;
+            virtualcam_rs::still::stream(&mut camera, path, options.fit, rate).map_err(fail)?;
+        }
+        #[cfg(not(feature = "image"))]
+        "image" => return Err("vcam was built without the `image` feature".to_owned()),
+        "play" => {
+            let path = first_arg(&options, "a path or -")?;
+            let input = source::open_input(&path).map_err(fail)?;
+            let mut source: Box<dyn VideoSource> = match options.format {
+                Some(format) => {
+                    let (width, height) = options.input_size.unwrap_or(options.size);
+                    Box::new(RawSource::new(input, width, height, format))
+                }
+                None => Box::new(Y4mSource::new(input).map_err(fail)?),
+            };
+            // the camera advertises the rate the stream is paced at
+            let rate = options.fps.or(source.frame_rate()).unwrap_or(DEFAULT_RATE);
+            let mut camera = open_camera(&options, rate).map_err(fail)?;
+            let frames = source::play(&mut camera, source.as_mut(), options.fit, Some(rate))
+                .map_err(fail)?;
+            eprintln!("played {} frames", frames);
+        }
+        #[cfg(target_os = "windows")]
+        "install" => {
+            let dll = PathBuf::from(first_arg(&options, "the filter DLL path")?);
+            unity_capture::install(&dll, options.device.as_deref(), options.devices)
+                .map_err(fail)?;
+        }
+        #[cfg(target_os = "windows")]
+        "uninstall" => {
+            let dll = PathBuf::from(first_arg(&options, "the filter DLL path")?);
+            unity_capture::uninstall(&dll).map_err(fail)?;
+        }
+        #[cfg(not(target_os = "windows"))]
+        "install" | "uninstall" => return Err("the UnityCapture filter is Windows-only".to_owned()),
+        "help" | "--help" | "-h" => print!("{}", USAGE),
+        _ => return Err(format!("unknown command {}\n\n{}", options.command, USAGE)),
+    }
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    match parse_args(std::env::args().skip(1)).and_then(run) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(message) => {
+            eprintln!("{}", message);
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,270 @@
+//! C ABI over [`Camera`]. The header is generated into `include/vcam.h` with
+//! `cbindgen --config cbindgen.toml --output include/vcam.h`.
+
+use std::cell::RefCell;
+use std::ffi::{c_char, CStr, CString};
+use std::ptr;
+use std::time::Duration;
+
+use crate::format::PixelFormat;
+use crate::mock::MockBackend;
+use crate::pacer::{FrameRate, Pacer};
+use crate::{Backend, Camera, Error, Frame};
+
+/// Result of every fallible `vcam_*` call.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VcamStatus {
+    Ok = 0,
+    InvalidArgument = 1,
+    DeviceNotFound = 2,
+    NotReady = 3,
+    FrameTooLarge = 4,
+    FrameSkipped = 5,
+    FrameDropped = 6,
+    Timeout = 7,
+    Unsupported = 8,
+    Io = 9,
+    Unknown = 10,
+    InUse = 11,
+    InvalidFrameSize = 12,
+}
+
+impl From<&Error> for VcamStatus {
+    fn from(e: &Error) -> Self {
+        match e {
+            Error::UnityCaptureNotFound => VcamStatus::DeviceNotFound,
+            Error::UnityCaptureNotRunning
+            | Error::UnityCaptureNotInitialized
+            | Error::ObsVcamNotRunning => VcamStatus::NotReady,
+            Error::SendresToolarge => VcamStatus::FrameTooLarge,
+            Error::InvalidFrameSize => VcamStatus::InvalidFrameSize,
+            Error::SendresWarnFrameskip => VcamStatus::FrameSkipped,
+            Error::FrameDropped | Error::QueueFull => VcamStatus::FrameDropped,
+            Error::MutexTimeout | Error::MutexAbandoned => VcamStatus::Timeout,
+            Error::UnsupportedFormat => VcamStatus::Unsupported,
+            Error::InvalidArgument | Error::InvalidStream(_) => VcamStatus::InvalidArgument,
+            Error::Io(_) | Error::ImageDecode(_) => VcamStatus::Io,
+            Error::ObsVcamInUse => VcamStatus::InUse,
+            _ => VcamStatus::Unknown,
+        }
+    }
+}
+
+/// Opaque camera handle.
+pub struct VcamCamera {
+    camera: Camera,
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<CString> = RefCell::new(CString::default());
+}
+
+fn fail(e: Error) -> VcamStatus {
+    let status = VcamStatus::from(&e);
+    let message = CString::new(format!("{:?}", e)).unwrap_or_default();
+    LAST_ERROR.with(|last| *last.borrow_mut() = message);
+    status
+}
+
+unsafe fn str_arg<'a>(s: *const c_char) -> Result<&'a str, Error> {
+    if s.is_null() {
+        return Err(Error::InvalidArgument);
+    }
+    CStr::from_ptr(s)
+        .to_str()
+        .map_err(|_| Error::InvalidArgument)
+}
+
+/// Opens `device` on `backend` at `width`×`height` and stores the handle in
+/// `*out`. Backends are `"unity"` (RGBA), `"obs"` (NV12 at 30 fps, `device`
+/// is ignored) and `"mock"`, an in-memory RGBA camera that accepts every
+/// frame. A null `device` picks the default device.
+///
+/// # Safety
+/// `backend` and `device` must be null or NUL-terminated strings, and `out`
+/// must be valid for a pointer write.
+#[no_mangle]
+pub unsafe extern "C" fn vcam_open(
+    backend: *const c_char,
+    device: *const c_char,
+    width: u32,
+    height: u32,
+    out: *mut *mut VcamCamera,
+) -> VcamStatus {
+    if out.is_null() {
+        return fail(Error::InvalidArgument);
+    }
+    *out = ptr::null_mut();
+    let backend = match backend.is_null() {
+        true => "unity",
+        false => match str_arg(backend) {
+            Ok(backend) => backend,
+            Err(e) => return fail(e),
+        },
+    };
+    let device = match device.is_null() {
+        true => "Unity Video Capture",
+        false => match str_arg(device) {
+            Ok(device) => device,
+            Err(e) => return fail(e),
+        },
+    };
+    let camera = match backend {
+        "unity" => Camera::new(width as i32, height as i32, device),
+        "obs" => FrameRate::new(30, 1).and_then(|rate| Camera::obs(width, height, rate)),
+        "mock" => Ok(Camera::from_backend(Backend::Mock(MockBackend::new(
+            width,
+            height,
+            PixelFormat::Rgba,
+        )))),
+        _ => Err(Error::InvalidArgument),
+    };
+    match camera {
+        Ok(camera) => {
+            *out = Box::into_raw(Box::new(VcamCamera { camera }));
+            VcamStatus::Ok
+        }
+        Err(e) => fail(e),
+    }
+}
+
+/// Sends `len` bytes in the camera's pixel format. A `timestamp` of 0 stamps
+/// the frame with the current time (100 ns units). A frame that is not
+/// exactly the camera's frame size is `VCAM_STATUS_INVALID_FRAME_SIZE`.
+///
+/// # Safety
+/// `camera` must come from `vcam_open`, and `data` must be valid for `len`
+/// bytes.
+#[no_mangle]
+pub unsafe extern "C" fn vcam_send(
+    camera: *mut VcamCamera,
+    data: *const u8,
+    len: usize,
+    timestamp: u64,
+) -> VcamStatus {
+    let Some(camera) = camera.as_mut() else {
+        return fail(Error::InvalidArgument);
+    };
+    if data.is_null() {
+        return fail(Error::InvalidArgument);
+    }
+    let data = std::slice::from_raw_parts(data, len).to_vec();
+    let frame = match timestamp {
+        0 => Frame::new(data),
+        timestamp => Frame::with_timestamp(data, timestamp),
+    };
+    match camera.camera.send(frame) {
+        Ok(()) => VcamStatus::Ok,
+        Err(e) => fail(e),
+    }
+}
+
+/// Sets a named option:
+/// - `fps`: pace frames at this rate, `0` to send immediately
+/// - `lock_timeout_ms`: UnityCapture mutex timeout, `-1` to wait forever
+///
+/// # Safety
+/// `camera` must come from `vcam_open`; `name` and `value` must be
+/// NUL-terminated strings.
+#[no_mangle]
+pub unsafe extern "C" fn vcam_set_option(
+    camera: *mut VcamCamera,
+    name: *const c_char,
+    value: *const c_char,
+) -> VcamStatus {
+    let Some(camera) = camera.as_mut() else {
+        return fail(Error::InvalidArgument);
+    };
+    let (name, value) = match (str_arg(name), str_arg(value)) {
+        (Ok(name), Ok(value)) => (name, value),
+        _ => return fail(Error::InvalidArgument),
+    };
+    match (name, value.parse::<f64>()) {
+        ("fps", Ok(0.0)) => camera.camera.set_pacer(None),
+        ("fps", Ok(fps)) => match Pacer::from_fps(fps) {
+            Ok(pacer) => camera.camera.set_pacer(Some(pacer)),
+            Err(e) => return fail(e),
+        },
+        ("lock_timeout_ms", Ok(ms)) => {
+            // only UnityCapture takes a lock
+            #[cfg_attr(not(target_os = "windows"), allow(unused_variables))]
+            let timeout = match ms < 0.0 {
+                true => None,
+                false => Some(Duration::from_millis(ms as u64)),
+            };
+            match camera.camera.backend_mut() {
+                #[cfg(target_os = "windows")]
+                Backend::UnityCapture(unity_capture) => unity_capture.set_lock_timeout(timeout),
+                #[cfg(target_os = "windows")]
+                Backend::ObsVcam(_) => {}
+                Backend::Mock(_) | Backend::FileSink(_) => {}
+            }
+        }
+        _ => return fail(Error::InvalidArgument),
+    }
+    VcamStatus::Ok
+}
+
+/// Writes the registered device names, one per line and NUL-terminated, into
+/// `buf` if it holds `len` bytes or more. Returns the size needed, including
+/// the terminator, so callers can size `buf` with a first call passing null.
+///
+/// # Safety
+/// `buf` must be null or valid for `len` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn vcam_list_devices(buf: *mut c_char, len: usize) -> usize {
+    #[cfg(target_os = "windows")]
+    let names: Vec<String> = crate::unity_capture::list_devices()
+        .into_iter()
+        .map(|(_, name)| name)
+        .collect();
+    #[cfg(not(target_os = "windows"))]
+    let names: Vec<String> = Vec::new();
+    let list = names.join("\n");
+    let needed = list.len() + 1;
+    if !buf.is_null() && len >= needed {
+        ptr::copy_nonoverlapping(list.as_ptr(), buf as *mut u8, list.len());
+        *buf.add(list.len()) = 0;
+    }
+    needed
+}
+
+/// Closes a camera. Null is ignored.
+///
+/// # Safety
+/// `camera` must come from `vcam_open` and not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn vcam_close(camera: *mut VcamCamera) {
+    if !camera.is_null() {
+        drop(Box::from_raw(camera));
+    }
+}
+
+/// Static description of a status code.
+#[no_mangle]
+pub extern "C" fn vcam_status_string(status: i32) -> *const c_char {
+    let s: &'static CStr = match status {
+        0 => c"ok",
+        1 => c"invalid argument",
+        2 => c"device not found",
+        3 => c"no consumer attached",
+        4 => c"frame too large",
+        5 => c"frame skipped by consumer",
+        6 => c"frame dropped",
+        7 => c"timed out waiting for consumer",
+        8 => c"unsupported",
+        9 => c"i/o error",
+        11 => c"device in use by another producer",
+        12 => c"frame is not the camera's size",
+        _ => c"unknown error",
+    };
+    s.as_ptr()
+}
+
+/// Details of the last error on this thread, valid until the next failing
+/// call on the same thread.
+#[no_mangle]
+pub extern "C" fn vcam_last_error() -> *const c_char {
+    LAST_ERROR.with(|last| last.borrow().as_ptr())
+}
@@ -0,0 +1,203 @@
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use crate::format::{convert, PixelFormat};
+use crate::pacer::FrameRate;
+use crate::Error;
+
+enum Container {
+    /// YUV4MPEG2, converted to I420; each frame header carries its timestamp
+    /// as an `Xts=` parameter, which readers ignore.
+    Y4m,
+    /// Frames back to back in the input format, described by a JSON file
+    /// next to the dump.
+    Raw {
+        sidecar: PathBuf,
+        timestamps: Vec<u64>,
+    },
+}
+
+/// Backend that writes every frame to a file instead of a device, so a
+/// session can be inspected or replayed with `vcam play`.
+///
+/// A path ending in `.y4m` gets a YUV4MPEG2 stream; anything else gets a raw
+/// dump plus `<path>.json` with the geometry, format and frame timestamps.
+pub struct FileSink {
+    width: u32,
+    height: u32,
+    format: PixelFormat,
+    frame_rate: FrameRate,
+    writer: BufWriter<File>,
+    container: Container,
+    frames: u64,
+}
+
+impl FileSink {
+    /// Creates `path`, accepting frames of `width`×`height` in `format`.
+    pub fn create<P: AsRef<Path>>(
+        path: P,
+        width: u32,
+        height: u32,
+        format: PixelFormat,
+    ) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let container = match path.extension().and_then(|ext| ext.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("y4m") => Container::Y4m,
+            _ => {
+                let mut sidecar = path.as_os_str().to_owned();
+                sidecar.push(".json");
+                Container::Raw {
+                    sidecar: sidecar.into(),
+                    timestamps: Vec::new(),
+                }
+            }
+        };
+        let file = File::create(path).map_err(Error::Io)?;
+        Ok(Self {
+            width,
+            height,
+            format,
+            frame_rate: FrameRate::from_integer(30),
+            writer: BufWriter::new(file),
+            container,
+            frames: 0,
+        })
+    }
+
+    /// Nominal frame rate recorded in the file, 30 fps by default. Must be
+    /// set before the first frame.
+    pub fn frame_rate(mut self, rate: FrameRate) -> Self {
+        self.frame_rate = rate;
+        self
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn format(&self) -> PixelFormat {
+        self.format
+    }
+
+    /// Frames written so far.
+    pub fn frames(&self) -> u64 {
+        self.frames
+    }
+
+    pub fn send(&mut self, data: &[u8], timestamp: u64) -> Result<(), Error> {
+        if data.len() != self.format.frame_size(self.width, self.height) {
+            return Err(Error::InvalidFrameSize);
+        }
+        match &mut self.container {
+            Container::Y4m => {
+                if self.frames == 0 {
+                    writeln!(
+                        self.writer,
+                        "YUV4MPEG2 W{} H{} F{}:{} Ip A1:1 C420jpeg",
+                        self.width,
+                        self.height,
+                        self.frame_rate.numerator(),
+                        self.frame_rate.denominator()
+                    )
+                    .map_err(Error::Io)?;
+                }
+                writeln!(self.writer, "FRAME Xts={}", timestamp).map_err(Error::Io)?;
+                match self.format {
+                    PixelFormat::I420 => self.writer.write_all(data),
+                    from => {
+                        let i420 = convert(data, from, PixelFormat::I420, self.width, self.height)?;
+                        self.writer.write_all(&i420)
+                    }
+                }
+                .map_err(Error::Io)?;
+            }
+            Container::Raw { timestamps, .. } => {
+                self.writer.write_all(data).map_err(Error::Io)?;
+                timestamps.push(timestamp);
+            }
+        }
+        self.frames += 1;
+        Ok(())
+    }
+
+    /// Flushes the file and writes the sidecar of a raw dump.
+    pub fn flush(&mut self) -> Result<(), Error> {
+        self.writer.flush().map_err(Error::Io)?;
+        if let Container::Raw {
+            sidecar,
+            timestamps,
+        } = &self.container
+        {
+            let timestamps: Vec<String> = timestamps.iter().map(|ts| ts.to_string()).collect();
+            let json = format!(
+                "{{\n  \"width\": {},\n  \"height\": {},\n  \"format\": \"{}\",\n  \"frame_size\": {},\n  \"frame_rate\": [{}, {}],\n  \"timestamps\": [{}]\n}}\n",
+                self.width,
+                self.height,
+                self.format.name(),
+                self.format.frame_size(self.width, self.height),
+                self.frame_rate.numerator(),
+                self.frame_rate.denominator(),
+                timestamps.join(", ")
+            );
+            fs::write(sidecar, json).map_err(Error::Io)?;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for FileSink {
+    fn drop(&mut self) {
+        let _ = self.flush();
+    }
+}
+
+#[test]
+fn test_file_sink_round_trip() {
+    use crate::source::{open_y4m, VideoSource};
+
+    let dir = std::env::temp_dir();
+    let y4m_path = dir.join(format!("virtualcam-sink-{}.y4m", std::process::id()));
+    let raw_path = dir.join(format!("virtualcam-sink-{}.rgba", std::process::id()));
+    let gray = [128u8, 128, 128, 255].repeat(4);
+
+    let mut y4m = FileSink::create(&y4m_path, 2, 2, PixelFormat::Rgba)
+        .unwrap()
+        .frame_rate(FrameRate::new(25, 1).unwrap());
+    let mut raw = FileSink::create(&raw_path, 2, 2, PixelFormat::Rgba).unwrap();
+    for ts in [100, 500] {
+        y4m.send(&gray, ts).unwrap();
+        raw.send(&gray, ts).unwrap();
+    }
+    assert!(matches!(
+        y4m.send(&gray[1..], 900),
+        Err(Error::InvalidFrameSize)
+    ));
+    drop((y4m, raw));
+
+    let mut source = open_y4m(&y4m_path).unwrap();
+    assert_eq!(source.frame_rate(), FrameRate::new(25, 1).ok());
+    assert_eq!(source.format(), PixelFormat::I420);
+    let frame = source.next_frame().unwrap().unwrap();
+    assert_eq!(
+        convert(&frame, PixelFormat::I420, PixelFormat::Rgba, 2, 2).unwrap(),
+        gray
+    );
+    assert!(source.next_frame().unwrap().is_some());
+    assert!(source.next_frame().unwrap().is_none());
+
+    assert_eq!(fs::read(&raw_path).unwrap(), gray.repeat(2));
+    let mut sidecar = raw_path.clone().into_os_string();
+    sidecar.push(".json");
+    let json = fs::read_to_string(&sidecar).unwrap();
+    assert!(json.contains("\"format\": \"rgba\""));
+    assert!(json.contains("\"timestamps\": [100, 500]"));
+
+    for path in [y4m_path, raw_path, sidecar.into()] {
+        fs::remove_file(path).ok();
+    }
+}
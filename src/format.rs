@@ -0,0 +1,231 @@
+use std::str::FromStr;
+
+use crate::Error;
+
+/// Pixel layouts understood by the backends and converters.
+///
+/// Packed formats are tightly packed rows; `Nv12` and `I420` are 4:2:0
+/// planar with BT.601 limited-range YUV, chroma planes rounded up for odd
+/// sizes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PixelFormat {
+    Rgba,
+    Bgra,
+    Rgb,
+    Bgr,
+    Nv12,
+    I420,
+}
+
+impl PixelFormat {
+    /// Bytes per pixel for packed formats, `None` for planar ones.
+    pub fn bytes_per_pixel(self) -> Option<usize> {
+        match self {
+            PixelFormat::Rgba | PixelFormat::Bgra => Some(4),
+            PixelFormat::Rgb | PixelFormat::Bgr => Some(3),
+            PixelFormat::Nv12 | PixelFormat::I420 => None,
+        }
+    }
+
+    pub fn frame_size(self, width: u32, height: u32) -> usize {
+        let (w, h) = (width as usize, height as usize);
+        match self.bytes_per_pixel() {
+            Some(bpp) => w * h * bpp,
+            None => w * h + chroma_width(width) * chroma_height(height) * 2,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            PixelFormat::Rgba => "rgba",
+            PixelFormat::Bgra => "bgra",
+            PixelFormat::Rgb => "rgb",
+            PixelFormat::Bgr => "bgr",
+            PixelFormat::Nv12 => "nv12",
+            PixelFormat::I420 => "i420",
+        }
+    }
+}
+
+impl FromStr for PixelFormat {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Error> {
+        match s.to_ascii_lowercase().as_str() {
+            "rgba" => Ok(PixelFormat::Rgba),
+            "bgra" => Ok(PixelFormat::Bgra),
+            "rgb" | "rgb24" => Ok(PixelFormat::Rgb),
+            "bgr" | "bgr24" => Ok(PixelFormat::Bgr),
+            "nv12" => Ok(PixelFormat::Nv12),
+            "i420" | "yuv420p" => Ok(PixelFormat::I420),
+            _ => Err(Error::UnsupportedFormat),
+        }
+    }
+}
+
+fn chroma_width(width: u32) -> usize {
+    width.div_ceil(2) as usize
+}
+
+fn chroma_height(height: u32) -> usize {
+    height.div_ceil(2) as usize
+}
+
+/// Converts a frame between formats. Returns `Error::InvalidFrameSize` when
+/// `src` does not hold a full `width`×`height` frame of `from`.
+pub fn convert(
+    src: &[u8],
+    from: PixelFormat,
+    to: PixelFormat,
+    width: u32,
+    height: u32,
+) -> Result<Vec<u8>, Error> {
+    if src.len() < from.frame_size(width, height) {
+        return Err(Error::InvalidFrameSize);
+    }
+    if from == to {
+        return Ok(src[..from.frame_size(width, height)].to_vec());
+    }
+    let rgba = to_rgba(src, from, width, height);
+    Ok(from_rgba(&rgba, to, width, height))
+}
+
+fn to_rgba(src: &[u8], from: PixelFormat, width: u32, height: u32) -> Vec<u8> {
+    let (w, h) = (width as usize, height as usize);
+    let mut rgba = vec![255; w * h * 4];
+    match from {
+        PixelFormat::Rgba => rgba.copy_from_slice(&src[..w * h * 4]),
+        PixelFormat::Bgra => {
+            for (dst, px) in rgba.chunks_exact_mut(4).zip(src.chunks_exact(4)) {
+                dst.copy_from_slice(&[px[2], px[1], px[0], px[3]]);
+            }
+        }
+        PixelFormat::Rgb => {
+            for (dst, px) in rgba.chunks_exact_mut(4).zip(src.chunks_exact(3)) {
+                dst[..3].copy_from_slice(px);
+            }
+        }
+        PixelFormat::Bgr => {
+            for (dst, px) in rgba.chunks_exact_mut(4).zip(src.chunks_exact(3)) {
+                dst[..3].copy_from_slice(&[px[2], px[1], px[0]]);
+            }
+        }
+        PixelFormat::Nv12 | PixelFormat::I420 => {
+            let cw = chroma_width(width);
+            let ch = chroma_height(height);
+            let (luma, chroma) = src.split_at(w * h);
+            for y in 0..h {
+                for x in 0..w {
+                    let c = (y / 2) * cw + x / 2;
+                    let (u, v) = match from {
+                        PixelFormat::Nv12 => (chroma[c * 2], chroma[c * 2 + 1]),
+                        _ => (chroma[c], chroma[cw * ch + c]),
+                    };
+                    let [r, g, b] = yuv_to_rgb(luma[y * w + x], u, v);
+                    rgba[(y * w + x) * 4..][..3].copy_from_slice(&[r, g, b]);
+                }
+            }
+        }
+    }
+    rgba
+}
+
+fn from_rgba(rgba: &[u8], to: PixelFormat, width: u32, height: u32) -> Vec<u8> {
+    let (w, h) = (width as usize, height as usize);
+    match to {
+        PixelFormat::Rgba => rgba.to_vec(),
+        PixelFormat::Bgra => rgba
+            .chunks_exact(4)
+            .flat_map(|px| [px[2], px[1], px[0], px[3]])
+            .collect(),
+        PixelFormat::Rgb => rgba
+            .chunks_exact(4)
+            .flat_map(|px| [px[0], px[1], px[2]])
+            .collect(),
+        PixelFormat::Bgr => rgba
+            .chunks_exact(4)
+            .flat_map(|px| [px[2], px[1], px[0]])
+            .collect(),
+        PixelFormat::Nv12 | PixelFormat::I420 => {
+            let cw = chroma_width(width);
+            let ch = chroma_height(height);
+            let mut out = vec![0; to.frame_size(width, height)];
+            let (luma, chroma) = out.split_at_mut(w * h);
+            for (dst, px) in luma.iter_mut().zip(rgba.chunks_exact(4)) {
+                *dst = rgb_to_yuv(px[0], px[1], px[2]).0;
+            }
+            for cy in 0..ch {
+                for cx in 0..cw {
+                    // average the chroma of the (up to) 2x2 block
+                    let (mut u_sum, mut v_sum, mut n) = (0u32, 0u32, 0u32);
+                    for y in cy * 2..(cy * 2 + 2).min(h) {
+                        for x in cx * 2..(cx * 2 + 2).min(w) {
+                            let px = &rgba[(y * w + x) * 4..];
+                            let (_, u, v) = rgb_to_yuv(px[0], px[1], px[2]);
+                            u_sum += u as u32;
+                            v_sum += v as u32;
+                            n += 1;
+                        }
+                    }
+                    let (u, v) = ((u_sum / n) as u8, (v_sum / n) as u8);
+                    let c = cy * cw + cx;
+                    match to {
+                        PixelFormat::Nv12 => {
+                            chroma[c * 2] = u;
+                            chroma[c * 2 + 1] = v;
+                        }
+                        _ => {
+                            chroma[c] = u;
+                            chroma[cw * ch + c] = v;
+                        }
+                    }
+                }
+            }
+            out
+        }
+    }
+}
+
+/// BT.601 limited range.
+pub fn rgb_to_yuv(r: u8, g: u8, b: u8) -> (u8, u8, u8) {
+    let (r, g, b) = (r as i32, g as i32, b as i32);
+    let y = ((66 * r + 129 * g + 25 * b + 128) >> 8) + 16;
+    let u = ((-38 * r - 74 * g + 112 * b + 128) >> 8) + 128;
+    let v = ((112 * r - 94 * g - 18 * b + 128) >> 8) + 128;
+    (y as u8, u as u8, v as u8)
+}
+
+/// BT.601 limited range.
+pub fn yuv_to_rgb(y: u8, u: u8, v: u8) -> [u8; 3] {
+    let c = y as i32 - 16;
+    let d = u as i32 - 128;
+    let e = v as i32 - 128;
+    let clip = |x: i32| x.clamp(0, 255) as u8;
+    [
+        clip((298 * c + 409 * e + 128) >> 8),
+        clip((298 * c - 100 * d - 208 * e + 128) >> 8),
+        clip((298 * c + 516 * d + 128) >> 8),
+    ]
+}
+
+#[test]
+fn test_convert_round_trip() {
+    let (width, height) = (3, 3);
+    let rgba: Vec<u8> = (0..width * height)
+        .flat_map(|_| [200, 40, 90, 255])
+        .collect();
+    for format in [PixelFormat::Bgr, PixelFormat::Nv12, PixelFormat::I420] {
+        let converted = convert(&rgba, PixelFormat::Rgba, format, width, height).unwrap();
+        assert_eq!(converted.len(), format.frame_size(width, height));
+        let back = convert(&converted, format, PixelFormat::Rgba, width, height).unwrap();
+        for (a, b) in rgba.iter().zip(&back) {
+            assert!(
+                (*a as i32 - *b as i32).abs() <= 2,
+                "{:?}: {} vs {}",
+                format,
+                a,
+                b
+            );
+        }
+    }
+}
@@ -0,0 +1,172 @@
+use crate::format::PixelFormat;
+#[cfg(any(feature = "image", feature = "ndarray"))]
+use crate::scale::{scale, FitMode};
+use crate::{Error, Frame};
+
+/// Images that can be turned into a frame of a given size and format.
+///
+/// Implemented for `image::RgbImage`/`RgbaImage` with the `image` feature and
+/// for `ndarray` `(height, width, channels)` arrays with the `ndarray`
+/// feature. Sources of a different size are fitted with black bars.
+pub trait IntoFrame {
+    fn into_frame(self, width: u32, height: u32, format: PixelFormat) -> Result<Frame, Error>;
+}
+
+/// Marks an array whose channels are stored BGR(A), as OpenCV produces them.
+pub struct Bgr<T>(pub T);
+
+impl IntoFrame for Frame {
+    fn into_frame(self, _width: u32, _height: u32, _format: PixelFormat) -> Result<Frame, Error> {
+        Ok(self)
+    }
+}
+
+/// Scales and converts a tightly packed frame to the target geometry.
+#[cfg(any(feature = "image", feature = "ndarray"))]
+fn packed_into_frame(
+    data: &[u8],
+    src_format: PixelFormat,
+    src_width: u32,
+    src_height: u32,
+    width: u32,
+    height: u32,
+    format: PixelFormat,
+) -> Result<Frame, Error> {
+    let data = scale(
+        data,
+        src_format,
+        src_width,
+        src_height,
+        format,
+        width,
+        height,
+        FitMode::Fit,
+    )?;
+    Ok(Frame::new(data))
+}
+
+#[cfg(feature = "image")]
+mod image_impls {
+    use image::{RgbImage, RgbaImage};
+
+    use super::{packed_into_frame, IntoFrame};
+    use crate::format::PixelFormat;
+    use crate::{Error, Frame};
+
+    impl IntoFrame for &RgbImage {
+        fn into_frame(self, width: u32, height: u32, format: PixelFormat) -> Result<Frame, Error> {
+            let (w, h) = self.dimensions();
+            packed_into_frame(self.as_raw(), PixelFormat::Rgb, w, h, width, height, format)
+        }
+    }
+
+    impl IntoFrame for RgbImage {
+        fn into_frame(self, width: u32, height: u32, format: PixelFormat) -> Result<Frame, Error> {
+            (&self).into_frame(width, height, format)
+        }
+    }
+
+    impl IntoFrame for &RgbaImage {
+        fn into_frame(self, width: u32, height: u32, format: PixelFormat) -> Result<Frame, Error> {
+            let (w, h) = self.dimensions();
+            packed_into_frame(
+                self.as_raw(),
+                PixelFormat::Rgba,
+                w,
+                h,
+                width,
+                height,
+                format,
+            )
+        }
+    }
+
+    impl IntoFrame for RgbaImage {
+        fn into_frame(self, width: u32, height: u32, format: PixelFormat) -> Result<Frame, Error> {
+            (&self).into_frame(width, height, format)
+        }
+    }
+}
+
+#[cfg(feature = "ndarray")]
+mod ndarray_impls {
+    use ndarray::{Array3, ArrayView3};
+
+    use super::{packed_into_frame, Bgr, IntoFrame};
+    use crate::format::PixelFormat;
+    use crate::{Error, Frame};
+
+    /// Packs a `(height, width, channels)` view in logical order, whatever
+    /// its strides, and picks the matching format.
+    fn pack(view: ArrayView3<u8>, bgr: bool) -> Result<(Vec<u8>, PixelFormat, u32, u32), Error> {
+        let (h, w, channels) = view.dim();
+        let format = match (channels, bgr) {
+            (3, false) => PixelFormat::Rgb,
+            (3, true) => PixelFormat::Bgr,
+            (4, false) => PixelFormat::Rgba,
+            (4, true) => PixelFormat::Bgra,
+            _ => return Err(Error::UnsupportedFormat),
+        };
+        let data = match view.as_slice() {
+            Some(slice) => slice.to_vec(),
+            None => view.iter().copied().collect(),
+        };
+        Ok((data, format, w as u32, h as u32))
+    }
+
+    impl IntoFrame for ArrayView3<'_, u8> {
+        fn into_frame(self, width: u32, height: u32, format: PixelFormat) -> Result<Frame, Error> {
+            let (data, src_format, w, h) = pack(self, false)?;
+            packed_into_frame(&data, src_format, w, h, width, height, format)
+        }
+    }
+
+    impl IntoFrame for &Array3<u8> {
+        fn into_frame(self, width: u32, height: u32, format: PixelFormat) -> Result<Frame, Error> {
+            self.view().into_frame(width, height, format)
+        }
+    }
+
+    impl IntoFrame for Array3<u8> {
+        fn into_frame(self, width: u32, height: u32, format: PixelFormat) -> Result<Frame, Error> {
+            self.view().into_frame(width, height, format)
+        }
+    }
+
+    impl IntoFrame for Bgr<ArrayView3<'_, u8>> {
+        fn into_frame(self, width: u32, height: u32, format: PixelFormat) -> Result<Frame, Error> {
+            let (data, src_format, w, h) = pack(self.0, true)?;
+            packed_into_frame(&data, src_format, w, h, width, height, format)
+        }
+    }
+
+    impl IntoFrame for Bgr<&Array3<u8>> {
+        fn into_frame(self, width: u32, height: u32, format: PixelFormat) -> Result<Frame, Error> {
+            Bgr(self.0.view()).into_frame(width, height, format)
+        }
+    }
+
+    impl IntoFrame for Bgr<Array3<u8>> {
+        fn into_frame(self, width: u32, height: u32, format: PixelFormat) -> Result<Frame, Error> {
+            Bgr(self.0.view()).into_frame(width, height, format)
+        }
+    }
+}
+
+#[cfg(all(feature = "image", feature = "ndarray"))]
+#[test]
+fn test_into_frame() {
+    let image = image::RgbImage::from_pixel(2, 2, image::Rgb([10, 20, 30]));
+    let frame = image.into_frame(2, 2, PixelFormat::Bgra).unwrap();
+    assert_eq!(frame.data, [30, 20, 10, 255].repeat(4));
+
+    // a transposed BGR array exercises the strided path
+    let array =
+        ndarray::Array3::from_shape_fn((3, 2, 3), |(y, x, c)| (y * 6 + x * 3 + c + 1) as u8);
+    let transposed = array.view().permuted_axes([1, 0, 2]);
+    let frame = Bgr(transposed).into_frame(3, 2, PixelFormat::Rgb).unwrap();
+    assert_eq!(
+        frame.data,
+        [3, 2, 1, 9, 8, 7, 15, 14, 13, 6, 5, 4, 12, 11, 10, 18, 17, 16]
+    );
+}
@@ -1,11 +1,95 @@
+use std::time::Duration;
+
+use file_sink::FileSink;
+use format::PixelFormat;
+use into_frame::IntoFrame;
+use metadata::FrameMetadata;
+use mock::MockBackend;
+#[cfg(target_os = "windows")]
+use obs_vcam::ObsVideoQueue;
+use pacer::{now_100ns, FrameRate, Pace, Pacer};
+use stats::{CameraStats, StatsRecorder};
+#[cfg(target_os = "windows")]
 use unity_capture::UnityCapture;
 
+#[cfg(feature = "async")]
+pub mod async_camera;
+pub mod capi;
+pub mod file_sink;
+pub mod format;
+pub mod into_frame;
+pub mod metadata;
+pub mod mock;
+pub mod multi;
 pub mod obs_vcam;
+pub mod pacer;
+pub mod patterns;
+#[cfg(feature = "python")]
+mod python;
+pub mod scale;
+pub mod source;
+pub mod stats;
+#[cfg(feature = "image")]
+pub mod still;
+#[cfg(target_os = "windows")]
 pub mod unity_capture;
+pub mod wide;
+pub mod worker;
 
-pub enum Camera {
+pub enum Backend {
+    #[cfg(target_os = "windows")]
     UnityCapture(UnityCapture),
-    // ObsVcam(obs_vcam::ObsVcam)
+    #[cfg(target_os = "windows")]
+    ObsVcam(ObsVideoQueue),
+    Mock(MockBackend),
+    FileSink(FileSink),
+}
+
+pub struct Camera {
+    backend: Backend,
+    pacer: Option<Pacer>,
+    stats: StatsRecorder,
+}
+
+/// A frame submitted to a [`Camera`].
+///
+/// `timestamp` is the presentation time in 100 ns units on the
+/// [`now_100ns`] clock; `None` stamps the frame when it is sent.
+/// `metadata` travels with the frame to receivers that read it back.
+#[derive(Debug, Clone, Default)]
+pub struct Frame {
+    pub data: Vec<u8>,
+    pub timestamp: Option<u64>,
+    pub metadata: Option<FrameMetadata>,
+}
+
+impl Frame {
+    pub fn new(data: Vec<u8>) -> Self {
+        Self {
+            data,
+            timestamp: None,
+            metadata: None,
+        }
+    }
+
+    pub fn with_timestamp(data: Vec<u8>, timestamp: u64) -> Self {
+        Self {
+            data,
+            timestamp: Some(timestamp),
+            metadata: None,
+        }
+    }
+
+    pub fn metadata(mut self, metadata: FrameMetadata) -> Self {
+        self.metadata = Some(metadata);
+        self
+    }
+}
+
+impl From<Vec<u8>> for Frame {
+    fn from(data: Vec<u8>) -> Self {
+        Self::new(data)
+    }
 }
 
 pub enum Error {
@@ -16,6 +100,19 @@ pub enum Error {
     UnityCaptureUnknownError,
     SendresToolarge,
     SendresWarnFrameskip,
+    FrameDropped,
+    QueueFull,
+    WorkerStopped,
+    MutexTimeout,
+    MutexAbandoned,
+    UnsupportedFormat,
+    InvalidFrameSize,
+    InvalidArgument,
+    Io(std::io::Error),
+    ImageDecode(String),
+    InvalidStream(String),
+    ObsVcamNotRunning,
+    ObsVcamInUse,
 }
 impl std::fmt::Debug for Error {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
@@ -27,25 +124,219 @@ impl std::fmt::Debug for Error {
             Error::UnityCaptureUnknownError => "UnityCaptureUnknownError",
             Error::SendresToolarge => "SendresToolarge",
             Error::SendresWarnFrameskip => "SendresWarnFrameskip",
+            Error::FrameDropped => "FrameDropped",
+            Error::QueueFull => "QueueFull",
+            Error::WorkerStopped => "WorkerStopped",
+            Error::MutexTimeout => "MutexTimeout",
+            Error::MutexAbandoned => "MutexAbandoned",
+            Error::UnsupportedFormat => "UnsupportedFormat",
+            Error::InvalidFrameSize => "InvalidFrameSize",
+            Error::InvalidArgument => "InvalidArgument",
+            Error::ObsVcamNotRunning => "ObsVcamNotRunning",
+            Error::ObsVcamInUse => "ObsVcamInUse",
+            Error::Io(e) => return write!(f, "Io({})", e),
+            Error::ImageDecode(e) => return write!(f, "ImageDecode({})", e),
+            Error::InvalidStream(e) => return write!(f, "InvalidStream({})", e),
         };
         write!(f, "{}", msg)
     }
 }
 impl Camera {
+    #[cfg(target_os = "windows")]
     pub fn new(width: i32, height: i32, device: &str) -> Result<Self, Error> {
-        // TODO: Add windows support
-        #[cfg(target_os = "windows")]
         let unity_capture = UnityCapture::new(width, height, device.to_owned())?;
-        return Ok(Camera::UnityCapture(unity_capture));
+        Ok(Self::from_backend(Backend::UnityCapture(unity_capture)))
+    }
+
+    // TODO: Add macos support
+    #[cfg(not(target_os = "windows"))]
+    pub fn new(_width: i32, _height: i32, _device: &str) -> Result<Self, Error> {
+        Err(Error::UnityCaptureNotFound)
+    }
+
+    /// Publishes NV12 frames on the OBS virtual camera queue, advertised to
+    /// consumers at `rate`.
+    #[cfg(target_os = "windows")]
+    pub fn obs(width: u32, height: u32, rate: FrameRate) -> Result<Self, Error> {
+        let queue = ObsVideoQueue::create(width, height, rate)?;
+        Ok(Self::from_backend(Backend::ObsVcam(queue)))
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    pub fn obs(_width: u32, _height: u32, _rate: FrameRate) -> Result<Self, Error> {
+        Err(Error::ObsVcamNotRunning)
+    }
+
+    /// Like [`obs`](Self::obs), but while another producer or a lingering
+    /// consumer holds the queue, retries until it is free or `timeout` has
+    /// passed.
+    #[cfg(target_os = "windows")]
+    pub fn obs_when_free(
+        width: u32,
+        height: u32,
+        rate: FrameRate,
+        timeout: Duration,
+    ) -> Result<Self, Error> {
+        let queue = ObsVideoQueue::create_when_free(width, height, rate, timeout)?;
+        Ok(Self::from_backend(Backend::ObsVcam(queue)))
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    pub fn obs_when_free(
+        _width: u32,
+        _height: u32,
+        _rate: FrameRate,
+        _timeout: Duration,
+    ) -> Result<Self, Error> {
+        Err(Error::ObsVcamNotRunning)
+    }
+
+    pub fn from_backend(backend: Backend) -> Self {
+        let name = match &backend {
+            #[cfg(target_os = "windows")]
+            Backend::UnityCapture(_) => "unity",
+            #[cfg(target_os = "windows")]
+            Backend::ObsVcam(_) => "obs",
+            Backend::Mock(_) => "mock",
+            Backend::FileSink(_) => "file",
+        };
+        Camera {
+            backend,
+            pacer: None,
+            stats: StatsRecorder::new(name),
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        match &self.backend {
+            #[cfg(target_os = "windows")]
+            Backend::UnityCapture(unity_capture) => unity_capture.width as u32,
+            #[cfg(target_os = "windows")]
+            Backend::ObsVcam(queue) => queue.width(),
+            Backend::Mock(mock) => mock.width(),
+            Backend::FileSink(sink) => sink.width(),
+        }
+    }
+
+    pub fn height(&self) -> u32 {
+        match &self.backend {
+            #[cfg(target_os = "windows")]
+            Backend::UnityCapture(unity_capture) => unity_capture.height as u32,
+            #[cfg(target_os = "windows")]
+            Backend::ObsVcam(queue) => queue.height(),
+            Backend::Mock(mock) => mock.height(),
+            Backend::FileSink(sink) => sink.height(),
+        }
+    }
+
+    /// The pixel format `send` expects.
+    pub fn format(&self) -> PixelFormat {
+        match &self.backend {
+            #[cfg(target_os = "windows")]
+            Backend::UnityCapture(_) => PixelFormat::Rgba,
+            #[cfg(target_os = "windows")]
+            Backend::ObsVcam(_) => PixelFormat::Nv12,
+            Backend::Mock(mock) => mock.format(),
+            Backend::FileSink(sink) => sink.format(),
+        }
+    }
+
+    /// The frame rate the backend advertises to consumers, for backends
+    /// that advertise one.
+    pub fn frame_rate(&self) -> Option<FrameRate> {
+        match &self.backend {
+            #[cfg(target_os = "windows")]
+            Backend::ObsVcam(queue) => Some(queue.frame_rate()),
+            _ => None,
+        }
+    }
+
+    pub fn backend(&self) -> &Backend {
+        &self.backend
+    }
 
-        // TODO: Add macos support
-        #[cfg(target_os = "macos")]
-        Self
+    pub fn backend_mut(&mut self) -> &mut Backend {
+        &mut self.backend
     }
 
-    pub fn send(&mut self, data: Vec<u8>) -> Result<(), Error> {
-        match self {
-            Camera::UnityCapture(unity_capture) => unity_capture.send(data), // Camera::ObsVcam(obs_vcam) => obs_vcam.send(data),
+    /// Whether the consumer side is up and the camera can accept frames.
+    pub fn is_ready(&mut self) -> bool {
+        match &mut self.backend {
+            #[cfg(target_os = "windows")]
+            Backend::UnityCapture(unity_capture) => unity_capture.shared_mem.send_is_ready(),
+            #[cfg(target_os = "windows")]
+            Backend::ObsVcam(queue) => queue.state() != obs_vcam::QueueState::Stopping,
+            Backend::Mock(mock) => mock.is_connected(),
+            Backend::FileSink(_) => true,
         }
     }
+
+    /// Paces submitted frames by their timestamps; `None` sends every frame
+    /// as soon as it arrives.
+    pub fn set_pacer(&mut self, pacer: Option<Pacer>) {
+        self.pacer = pacer;
+    }
+
+    pub fn pacer(&self) -> Option<&Pacer> {
+        self.pacer.as_ref()
+    }
+
+    /// What the camera has sent so far.
+    pub fn stats(&self) -> CameraStats {
+        self.stats.snapshot()
+    }
+
+    pub fn reset_stats(&mut self) {
+        self.stats.reset();
+    }
+
+    /// Converts an image to the camera's size and format and sends it.
+    pub fn send_image<T: IntoFrame>(&mut self, image: T) -> Result<(), Error> {
+        let frame = image.into_frame(self.width(), self.height(), self.format())?;
+        self.send(frame)
+    }
+
+    /// Sends a frame, holding it until its timestamp is due when a pacer is
+    /// set. Frames the pacer considers too late return `Error::FrameDropped`.
+    ///
+    /// The OBS backend stores the frame's metadata in its frame header and
+    /// UnityCapture in a side mapping read by
+    /// [`MetadataReader`](unity_capture::MetadataReader); the file sink
+    /// ignores it.
+    pub fn send<F: Into<Frame>>(&mut self, frame: F) -> Result<(), Error> {
+        let frame = frame.into();
+        let timestamp = frame.timestamp.unwrap_or_else(now_100ns);
+        if let Some(pacer) = &mut self.pacer {
+            match pacer.pace(timestamp) {
+                Pace::Send => {}
+                Pace::Hold(wait) => std::thread::sleep(wait),
+                Pace::Drop => {
+                    self.stats.record_dropped();
+                    return Err(Error::FrameDropped);
+                }
+            }
+        }
+        let len = frame.data.len();
+        #[cfg(feature = "tracing")]
+        let _span =
+            tracing::debug_span!("vcam_send", backend = self.stats.backend(), len, timestamp)
+                .entered();
+        let start = std::time::Instant::now();
+        let result = match &mut self.backend {
+            #[cfg(target_os = "windows")]
+            Backend::UnityCapture(unity_capture) => {
+                unity_capture.send_with_metadata(frame.data, frame.metadata.as_ref())
+            }
+            #[cfg(target_os = "windows")]
+            Backend::ObsVcam(queue) => {
+                queue.write_with_metadata(&frame.data, timestamp, frame.metadata.as_ref())
+            }
+            Backend::Mock(mock) => {
+                mock.send_with_metadata(&frame.data, timestamp, frame.metadata.as_ref())
+            }
+            Backend::FileSink(sink) => sink.send(&frame.data, timestamp),
+        };
+        self.stats.record(&result, len, timestamp, start.elapsed());
+        result
+    }
 }
@@ -0,0 +1,223 @@
+use std::mem;
+use std::ptr::{self, NonNull};
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use crate::Error;
+
+/// Largest payload that fits in an OBS frame header.
+pub const OBS_MAX_PAYLOAD: usize = 6;
+/// Largest payload a [`MetadataBlock`] holds.
+pub const MAX_PAYLOAD: usize = 256;
+/// Marks metadata written by this crate, in `QueueHeader.reserved[0]` of an
+/// OBS queue and at the start of a [`MetadataBlock`].
+pub const METADATA_MAGIC: u32 = u32::from_le_bytes(*b"VCMD");
+/// Spare bytes after the timestamp in an OBS frame header.
+pub(crate) const OBS_METADATA_SIZE: usize = 24;
+const OBS_METADATA_PRESENT: u8 = 1;
+const READ_RETRIES: usize = 8;
+
+/// Caller-defined data sent along with a frame, so a receiver can match the
+/// frames it sees to the sender's own.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FrameMetadata {
+    pub frame_id: u64,
+    /// When the frame was captured, in 100 ns units; the crate does not
+    /// interpret it.
+    pub capture_timestamp: u64,
+    /// At most [`MAX_PAYLOAD`] bytes, or [`OBS_MAX_PAYLOAD`] on the OBS
+    /// backend.
+    pub payload: Vec<u8>,
+}
+
+impl FrameMetadata {
+    pub fn new(frame_id: u64, capture_timestamp: u64) -> Self {
+        Self {
+            frame_id,
+            capture_timestamp,
+            payload: Vec::new(),
+        }
+    }
+
+    pub fn payload(mut self, payload: Vec<u8>) -> Self {
+        self.payload = payload;
+        self
+    }
+
+    /// Packs the metadata into the spare bytes of an OBS frame header.
+    pub(crate) fn to_obs_header(&self) -> Result<[u8; OBS_METADATA_SIZE], Error> {
+        if self.payload.len() > OBS_MAX_PAYLOAD {
+            return Err(Error::InvalidArgument);
+        }
+        let mut bytes = [0; OBS_METADATA_SIZE];
+        bytes[..8].copy_from_slice(&self.frame_id.to_le_bytes());
+        bytes[8..16].copy_from_slice(&self.capture_timestamp.to_le_bytes());
+        bytes[16] = OBS_METADATA_PRESENT;
+        bytes[17] = self.payload.len() as u8;
+        bytes[18..18 + self.payload.len()].copy_from_slice(&self.payload);
+        Ok(bytes)
+    }
+
+    pub(crate) fn from_obs_header(bytes: &[u8; OBS_METADATA_SIZE]) -> Option<Self> {
+        if bytes[16] != OBS_METADATA_PRESENT {
+            return None;
+        }
+        let len = (bytes[17] as usize).min(OBS_MAX_PAYLOAD);
+        Some(Self {
+            frame_id: u64::from_le_bytes(bytes[..8].try_into().unwrap()),
+            capture_timestamp: u64::from_le_bytes(bytes[8..16].try_into().unwrap()),
+            payload: bytes[18..18 + len].to_vec(),
+        })
+    }
+}
+
+#[repr(C)]
+struct BlockHeader {
+    magic: AtomicU32,
+    /// Odd while the writer is updating the block.
+    seq: AtomicU32,
+    frame_id: u64,
+    capture_timestamp: u64,
+    payload_len: u32,
+    reserved: u32,
+    payload: [u8; MAX_PAYLOAD],
+}
+
+const _: () = assert!(mem::size_of::<BlockHeader>() == 288);
+
+/// The metadata of the newest frame, laid over shared memory and guarded by
+/// a sequence counter so a reader never sees a half-written entry.
+///
+/// UnityCapture's own mapping has no room for metadata, so its backend keeps
+/// one of these in a separate mapping next to it.
+#[derive(Debug)]
+pub struct MetadataBlock {
+    base: NonNull<BlockHeader>,
+}
+
+unsafe impl Send for MetadataBlock {}
+
+impl MetadataBlock {
+    /// Size of the shared memory a block needs.
+    pub const SIZE: usize = mem::size_of::<BlockHeader>();
+
+    /// Attaches to a block; zeroed memory is an empty block.
+    ///
+    /// # Safety
+    /// `base` must be 8-byte aligned and valid for reads and writes of
+    /// [`SIZE`](Self::SIZE) bytes for as long as the block is used.
+    pub unsafe fn open(base: *mut u8) -> Result<Self, Error> {
+        let base = NonNull::new(base).ok_or(Error::InvalidArgument)?;
+        Ok(Self { base: base.cast() })
+    }
+
+    #[cfg(target_os = "windows")]
+    pub(crate) fn as_ptr(&self) -> *mut u8 {
+        self.base.as_ptr() as *mut u8
+    }
+
+    // Only the atomics are borrowed; the other fields are shared with
+    // another process and go through volatile accesses.
+    fn magic(&self) -> &AtomicU32 {
+        unsafe { &*ptr::addr_of!((*self.base.as_ptr()).magic) }
+    }
+
+    fn seq(&self) -> &AtomicU32 {
+        unsafe { &*ptr::addr_of!((*self.base.as_ptr()).seq) }
+    }
+
+    /// Replaces the stored metadata.
+    pub fn write(&mut self, metadata: &FrameMetadata) -> Result<(), Error> {
+        if metadata.payload.len() > MAX_PAYLOAD {
+            return Err(Error::InvalidArgument);
+        }
+        let seq = self.seq().load(Ordering::SeqCst);
+        self.seq().store(seq | 1, Ordering::SeqCst);
+        let block = self.base.as_ptr();
+        unsafe {
+            ptr::addr_of_mut!((*block).frame_id).write_volatile(metadata.frame_id);
+            ptr::addr_of_mut!((*block).capture_timestamp)
+                .write_volatile(metadata.capture_timestamp);
+            ptr::addr_of_mut!((*block).payload_len).write_volatile(metadata.payload.len() as u32);
+            metadata.payload.as_ptr().copy_to_nonoverlapping(
+                ptr::addr_of_mut!((*block).payload) as *mut u8,
+                metadata.payload.len(),
+            );
+        }
+        self.magic().store(METADATA_MAGIC, Ordering::SeqCst);
+        self.seq()
+            .store((seq | 1).wrapping_add(1), Ordering::SeqCst);
+        Ok(())
+    }
+
+    /// Copies out the stored metadata if it changed since `last_seq`.
+    /// `Ok(None)` means nothing new, or a writer kept the block busy.
+    pub fn read(&self, last_seq: &mut Option<u32>) -> Result<Option<FrameMetadata>, Error> {
+        match self.magic().load(Ordering::SeqCst) {
+            0 => return Ok(None),
+            METADATA_MAGIC => {}
+            _ => return Err(Error::InvalidStream("not a metadata block".to_owned())),
+        }
+        let block = self.base.as_ptr();
+        for _ in 0..READ_RETRIES {
+            let seq = self.seq().load(Ordering::SeqCst);
+            if *last_seq == Some(seq) {
+                return Ok(None);
+            }
+            if seq & 1 == 1 {
+                std::hint::spin_loop();
+                continue;
+            }
+            let mut payload = [0; MAX_PAYLOAD];
+            let metadata = unsafe {
+                let len = ptr::addr_of!((*block).payload_len).read_volatile() as usize;
+                let len = len.min(MAX_PAYLOAD);
+                (ptr::addr_of!((*block).payload) as *const u8)
+                    .copy_to_nonoverlapping(payload.as_mut_ptr(), len);
+                FrameMetadata {
+                    frame_id: ptr::addr_of!((*block).frame_id).read_volatile(),
+                    capture_timestamp: ptr::addr_of!((*block).capture_timestamp).read_volatile(),
+                    payload: payload[..len].to_vec(),
+                }
+            };
+            if self.seq().load(Ordering::SeqCst) == seq {
+                *last_seq = Some(seq);
+                return Ok(Some(metadata));
+            }
+        }
+        Ok(None)
+    }
+}
+
+#[test]
+fn test_frame_metadata_encoding() {
+    let metadata = FrameMetadata::new(7, 123_456).payload(b"cam1".to_vec());
+    let bytes = metadata.to_obs_header().unwrap();
+    assert_eq!(FrameMetadata::from_obs_header(&bytes), Some(metadata));
+    assert_eq!(
+        FrameMetadata::from_obs_header(&[0; OBS_METADATA_SIZE]),
+        None
+    );
+    assert!(FrameMetadata::new(1, 0)
+        .payload(vec![0; OBS_MAX_PAYLOAD + 1])
+        .to_obs_header()
+        .is_err());
+
+    let mut buf = vec![0u64; MetadataBlock::SIZE / 8];
+    let base = buf.as_mut_ptr() as *mut u8;
+    let mut writer = unsafe { MetadataBlock::open(base) }.unwrap();
+    let reader = unsafe { MetadataBlock::open(base) }.unwrap();
+    let mut last_seq = None;
+    assert_eq!(reader.read(&mut last_seq).unwrap(), None);
+
+    let first = FrameMetadata::new(1, 10).payload(vec![9; MAX_PAYLOAD]);
+    writer.write(&first).unwrap();
+    assert_eq!(reader.read(&mut last_seq).unwrap(), Some(first));
+    assert_eq!(reader.read(&mut last_seq).unwrap(), None);
+
+    let second = FrameMetadata::new(2, 20);
+    writer.write(&second).unwrap();
+    assert_eq!(reader.read(&mut last_seq).unwrap(), Some(second));
+    assert!(writer
+        .write(&FrameMetadata::new(3, 30).payload(vec![0; MAX_PAYLOAD + 1]))
+        .is_err());
+}
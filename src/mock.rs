@@ -0,0 +1,218 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use crate::format::PixelFormat;
+use crate::metadata::FrameMetadata;
+use crate::Error;
+
+/// A frame the mock backend received.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordedFrame {
+    /// Position among all sends, counting failed ones.
+    pub index: u64,
+    pub width: u32,
+    pub height: u32,
+    pub format: PixelFormat,
+    pub timestamp: u64,
+    pub len: usize,
+    /// FNV-1a hash of the frame bytes.
+    pub hash: u64,
+    pub metadata: Option<FrameMetadata>,
+}
+
+/// What the mock does with a send.
+#[derive(Debug)]
+pub enum MockAction {
+    /// Record the frame and accept it.
+    Accept,
+    /// Record the frame and report it as skipped by the consumer.
+    Skip,
+    /// Detach the consumer; this and later sends fail with
+    /// `Error::UnityCaptureNotInitialized` until a `Reconnect`.
+    Disconnect,
+    /// Attach the consumer again, then record and accept the frame.
+    Reconnect,
+    /// Fail the send with this error without recording the frame.
+    Fail(Error),
+    /// Panic inside the send, as a buggy backend might.
+    Panic,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    sends: u64,
+    connected: bool,
+    script: BTreeMap<u64, VecDeque<MockAction>>,
+    frames: Vec<RecordedFrame>,
+}
+
+/// In-memory backend that records every frame and replays scripted
+/// failures, for testing code built on [`Camera`](crate::Camera) without a
+/// driver.
+///
+/// Clones share their state, so a test can keep one clone to inspect
+/// after moving the camera into a worker or async wrapper.
+#[derive(Debug, Clone)]
+pub struct MockBackend {
+    width: u32,
+    height: u32,
+    format: PixelFormat,
+    state: Arc<Mutex<State>>,
+}
+
+impl MockBackend {
+    pub fn new(width: u32, height: u32, format: PixelFormat) -> Self {
+        Self {
+            width,
+            height,
+            format,
+            state: Arc::new(Mutex::new(State {
+                connected: true,
+                ..State::default()
+            })),
+        }
+    }
+
+    /// Scripts what happens to the `index`th send (0-based). Several actions
+    /// for the same send are applied in order; sends without one are
+    /// accepted.
+    pub fn on_send(self, index: u64, action: MockAction) -> Self {
+        self.state()
+            .script
+            .entry(index)
+            .or_default()
+            .push_back(action);
+        self
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn format(&self) -> PixelFormat {
+        self.format
+    }
+
+    pub fn is_connected(&self) -> bool {
+        self.state().connected
+    }
+
+    /// Attaches or detaches the consumer outside of a send, as a receiver
+    /// starting up or going away would.
+    pub fn set_connected(&self, connected: bool) {
+        self.state().connected = connected;
+    }
+
+    /// Number of sends so far, including failed ones.
+    pub fn sends(&self) -> u64 {
+        self.state().sends
+    }
+
+    /// Frames recorded so far.
+    pub fn frames(&self) -> Vec<RecordedFrame> {
+        self.state().frames.clone()
+    }
+
+    pub fn send(&mut self, data: &[u8], timestamp: u64) -> Result<(), Error> {
+        self.send_with_metadata(data, timestamp, None)
+    }
+
+    pub fn send_with_metadata(
+        &mut self,
+        data: &[u8],
+        timestamp: u64,
+        metadata: Option<&FrameMetadata>,
+    ) -> Result<(), Error> {
+        let mut state = self.state();
+        let index = state.sends;
+        state.sends += 1;
+
+        let mut skip = false;
+        for action in state.script.remove(&index).unwrap_or_default() {
+            match action {
+                MockAction::Accept => {}
+                MockAction::Skip => skip = true,
+                MockAction::Disconnect => state.connected = false,
+                MockAction::Reconnect => state.connected = true,
+                MockAction::Fail(e) => return Err(e),
+                MockAction::Panic => {
+                    drop(state);
+                    panic!("MockAction::Panic on send {}", index);
+                }
+            }
+        }
+        if !state.connected {
+            return Err(Error::UnityCaptureNotInitialized);
+        }
+        if data.len() != self.format.frame_size(self.width, self.height) {
+            return Err(Error::InvalidFrameSize);
+        }
+        state.frames.push(RecordedFrame {
+            index,
+            width: self.width,
+            height: self.height,
+            format: self.format,
+            timestamp,
+            len: data.len(),
+            hash: fnv1a(data),
+            metadata: metadata.cloned(),
+        });
+        match skip {
+            true => Err(Error::SendresWarnFrameskip),
+            false => Ok(()),
+        }
+    }
+
+    fn state(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap()
+    }
+}
+
+/// FNV-1a, so recorded hashes are stable across runs and platforms.
+pub fn fnv1a(data: &[u8]) -> u64 {
+    data.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &b| {
+        (hash ^ b as u64).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
+
+#[test]
+fn test_mock_backend_script() {
+    let mut mock = MockBackend::new(2, 2, PixelFormat::Rgba)
+        .on_send(1, MockAction::Skip)
+        .on_send(2, MockAction::Disconnect)
+        .on_send(4, MockAction::Reconnect)
+        .on_send(5, MockAction::Fail(Error::MutexTimeout));
+    let frame = [7u8; 16];
+
+    assert!(mock.send(&frame, 10).is_ok());
+    assert!(matches!(
+        mock.send(&frame, 20),
+        Err(Error::SendresWarnFrameskip)
+    ));
+    assert!(matches!(
+        mock.send(&frame, 30),
+        Err(Error::UnityCaptureNotInitialized)
+    ));
+    assert!(!mock.is_connected());
+    assert!(mock.send(&frame, 40).is_err());
+    assert!(mock.send(&frame, 50).is_ok());
+    assert!(matches!(mock.send(&frame, 60), Err(Error::MutexTimeout)));
+    assert!(matches!(
+        mock.send(&frame[..8], 70),
+        Err(Error::InvalidFrameSize)
+    ));
+
+    let frames = mock.frames();
+    assert_eq!(mock.sends(), 7);
+    assert_eq!(
+        frames.iter().map(|f| f.index).collect::<Vec<_>>(),
+        [0, 1, 4]
+    );
+    assert_eq!(frames[2].timestamp, 50);
+    assert_eq!(frames[0].hash, fnv1a(&frame));
+    assert_eq!((frames[0].width, frames[0].len), (2, 16));
+}
@@ -0,0 +1,178 @@
+use crate::format::PixelFormat;
+use crate::pacer::{now_100ns, Pace, Pacer};
+use crate::scale::{scale, FitMode};
+use crate::{Camera, Error, Frame};
+
+struct Output {
+    camera: Camera,
+    fit: FitMode,
+}
+
+/// Sends one feed to several cameras, converting and scaling it for each.
+///
+/// Every output gets the same timestamp. A failing output only affects its
+/// own entry in the results of [`send`](MultiCamera::send).
+pub struct MultiCamera {
+    width: u32,
+    height: u32,
+    format: PixelFormat,
+    outputs: Vec<Output>,
+    pacer: Option<Pacer>,
+}
+
+impl MultiCamera {
+    /// Creates a fan-out for input frames of `width`×`height` in `format`.
+    pub fn new(width: u32, height: u32, format: PixelFormat) -> Self {
+        Self {
+            width,
+            height,
+            format,
+            outputs: Vec::new(),
+            pacer: None,
+        }
+    }
+
+    /// Adds an output, fitting the input to it with `FitMode::Fit`, and
+    /// returns its index.
+    pub fn add(&mut self, camera: Camera) -> usize {
+        self.add_with_fit(camera, FitMode::Fit)
+    }
+
+    pub fn add_with_fit(&mut self, camera: Camera, fit: FitMode) -> usize {
+        self.outputs.push(Output { camera, fit });
+        self.outputs.len() - 1
+    }
+
+    /// Removes an output; later outputs move down one index.
+    pub fn remove(&mut self, index: usize) -> Camera {
+        self.outputs.remove(index).camera
+    }
+
+    pub fn output(&self, index: usize) -> Option<&Camera> {
+        self.outputs.get(index).map(|output| &output.camera)
+    }
+
+    pub fn output_mut(&mut self, index: usize) -> Option<&mut Camera> {
+        self.outputs.get_mut(index).map(|output| &mut output.camera)
+    }
+
+    pub fn len(&self) -> usize {
+        self.outputs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.outputs.is_empty()
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn format(&self) -> PixelFormat {
+        self.format
+    }
+
+    /// Paces frames once for all outputs. Outputs keep their own pacers,
+    /// which are best left unset.
+    pub fn set_pacer(&mut self, pacer: Option<Pacer>) {
+        self.pacer = pacer;
+    }
+
+    /// Sends a frame to every output and returns each output's result, in
+    /// output order. A frame the pacer drops returns `Error::FrameDropped`
+    /// without reaching any output.
+    pub fn send<F: Into<Frame>>(&mut self, frame: F) -> Result<Vec<Result<(), Error>>, Error> {
+        let frame = frame.into();
+        let timestamp = frame.timestamp.unwrap_or_else(now_100ns);
+        if frame.data.len() != self.format.frame_size(self.width, self.height) {
+            return Err(Error::InvalidFrameSize);
+        }
+        if let Some(pacer) = &mut self.pacer {
+            match pacer.pace(timestamp) {
+                Pace::Send => {}
+                Pace::Hold(wait) => std::thread::sleep(wait),
+                Pace::Drop => return Err(Error::FrameDropped),
+            }
+        }
+        let results = self
+            .outputs
+            .iter_mut()
+            .map(|output| {
+                let camera = &mut output.camera;
+                let (width, height, format) = (camera.width(), camera.height(), camera.format());
+                let data = match (width, height, format) == (self.width, self.height, self.format) {
+                    true => frame.data.clone(),
+                    false => scale(
+                        &frame.data,
+                        self.format,
+                        self.width,
+                        self.height,
+                        format,
+                        width,
+                        height,
+                        output.fit,
+                    )?,
+                };
+                camera.send(Frame {
+                    data,
+                    timestamp: Some(timestamp),
+                    metadata: frame.metadata.clone(),
+                })
+            })
+            .collect();
+        Ok(results)
+    }
+
+    /// Hands the outputs back, in order.
+    pub fn into_outputs(self) -> Vec<Camera> {
+        self.outputs
+            .into_iter()
+            .map(|output| output.camera)
+            .collect()
+    }
+}
+
+#[test]
+fn test_multi_camera_fan_out() {
+    use crate::mock::{MockAction, MockBackend};
+    use crate::Backend;
+
+    let same = MockBackend::new(4, 2, PixelFormat::Rgba);
+    let smaller = MockBackend::new(2, 2, PixelFormat::I420);
+    let failing =
+        MockBackend::new(4, 2, PixelFormat::Bgra).on_send(0, MockAction::Fail(Error::MutexTimeout));
+
+    let mut multi = MultiCamera::new(4, 2, PixelFormat::Rgba);
+    for mock in [&same, &smaller, &failing] {
+        multi.add(Camera::from_backend(Backend::Mock(mock.clone())));
+    }
+    let frame = [255u8, 0, 0, 255].repeat(8);
+    let results = multi
+        .send(Frame::with_timestamp(frame.clone(), 42))
+        .unwrap();
+    assert!(results[0].is_ok() && results[1].is_ok());
+    assert!(matches!(results[2], Err(Error::MutexTimeout)));
+
+    let metadata = crate::metadata::FrameMetadata::new(2, 80);
+    let results = multi
+        .send(Frame::with_timestamp(frame.clone(), 84).metadata(metadata.clone()))
+        .unwrap();
+    assert!(results.iter().all(|result| result.is_ok()));
+    assert!(matches!(
+        multi.send(vec![0; 3]),
+        Err(Error::InvalidFrameSize)
+    ));
+
+    assert_eq!(same.frames().len(), 2);
+    assert_eq!(same.frames()[0].hash, crate::mock::fnv1a(&frame));
+    assert_eq!(smaller.frames()[1].timestamp, 84);
+    assert_eq!(smaller.frames()[1].metadata, Some(metadata));
+    assert_eq!(same.frames()[0].metadata, None);
+    assert_eq!(smaller.frames()[0].len, 6);
+    assert_eq!(failing.frames().len(), 1);
+    assert_eq!(multi.into_outputs().len(), 3);
+}
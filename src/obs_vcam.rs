@@ -1,27 +1,118 @@
-use std::alloc::{alloc, Layout};
-use std::{mem, ptr};
+use std::mem;
+use std::ptr::NonNull;
+use std::sync::atomic::{AtomicU32, Ordering};
+#[cfg(target_os = "windows")]
+use std::{
+    ptr,
+    time::{Duration, Instant},
+};
+
+#[cfg(target_os = "windows")]
+use log::{debug, info};
+#[cfg(target_os = "windows")]
+use winapi::shared::winerror::ERROR_ALREADY_EXISTS;
+#[cfg(target_os = "windows")]
 use winapi::um::memoryapi::{OpenFileMappingW, FILE_MAP_READ};
-use winapi::{
-    shared::minwindef::DWORD,
-    um::{
-        handleapi::{CloseHandle, INVALID_HANDLE_VALUE},
-        memoryapi::{CreateFileMappingW, MapViewOfFile, FILE_MAP_ALL_ACCESS},
-        winnt::{HANDLE, PAGE_READWRITE},
+#[cfg(target_os = "windows")]
+use winapi::um::{
+    handleapi::{CloseHandle, INVALID_HANDLE_VALUE},
+    memoryapi::{
+        CreateFileMappingW, MapViewOfFile, UnmapViewOfFile, VirtualQuery, FILE_MAP_ALL_ACCESS,
     },
+    winnt::{HANDLE, MEMORY_BASIC_INFORMATION, PAGE_READWRITE},
 };
 
+use crate::metadata::{FrameMetadata, METADATA_MAGIC, OBS_METADATA_SIZE};
+#[cfg(target_os = "windows")]
+use crate::pacer::FrameRate;
+#[cfg(target_os = "windows")]
+use crate::wide::WideString;
+use crate::{Error, Frame};
+
+#[cfg(target_os = "windows")]
 const VIDEO_NAME: &str = "OBSVirtualCamVideo";
-const FRAME_HEADER_SIZE: u32 = 32;
+#[cfg(target_os = "windows")]
+const IN_USE_POLL: Duration = Duration::from_millis(100);
+const FRAME_HEADER_SIZE: usize = 32;
+/// Frame rates the OBS backend accepts.
+pub const MIN_FPS: u32 = 1;
+pub const MAX_FPS: u32 = 240;
+pub const SHARED_QUEUE_STATE_INVALID: u32 = 0;
+pub const SHARED_QUEUE_STATE_STARTING: u32 = 1;
+pub const SHARED_QUEUE_STATE_READY: u32 = 2;
+pub const SHARED_QUEUE_STATE_STOPPING: u32 = 3;
+pub const SHARED_QUEUE_TYPE_VIDEO: u32 = 0;
+
 fn align_size(mut size: usize, align: usize) -> usize {
     size = (size + align - 1) & !(align - 1);
     size
 }
 
+fn nv12_size(cx: u32, cy: u32) -> usize {
+    cx as usize * cy as usize * 3 / 2
+}
+
+/// [`nv12_size`] for a size read from a header another process may have
+/// written; `None` if it overflows.
+fn checked_nv12_size(cx: u32, cy: u32) -> Option<usize> {
+    Some((cx as usize).checked_mul(cy as usize)?.checked_mul(3)? / 2)
+}
+
+fn corrupt_header() -> Error {
+    Error::InvalidStream("corrupt OBS queue header".to_owned())
+}
+
+/// Offsets of the three frame slots and the total mapping size for a
+/// `cx`×`cy` queue, laid out as OBS's `video_queue_create` does.
+pub fn queue_layout(cx: u32, cy: u32) -> ([u32; 3], usize) {
+    let frame_size = nv12_size(cx, cy);
+    let mut offsets = [0; 3];
+    let mut size = align_size(mem::size_of::<QueueHeader>(), 32);
+    for offset in &mut offsets {
+        *offset = size as u32;
+        size = align_size(size + frame_size + FRAME_HEADER_SIZE, 32);
+    }
+    (offsets, size)
+}
+
+/// The `state` of an OBS shared queue.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QueueState {
+    Invalid,
+    Starting,
+    Ready,
+    Stopping,
+}
+
+impl QueueState {
+    pub fn from_raw(state: u32) -> Self {
+        match state {
+            SHARED_QUEUE_STATE_STARTING => QueueState::Starting,
+            SHARED_QUEUE_STATE_READY => QueueState::Ready,
+            SHARED_QUEUE_STATE_STOPPING => QueueState::Stopping,
+            _ => QueueState::Invalid,
+        }
+    }
+
+    pub fn raw(self) -> u32 {
+        match self {
+            QueueState::Invalid => SHARED_QUEUE_STATE_INVALID,
+            QueueState::Starting => SHARED_QUEUE_STATE_STARTING,
+            QueueState::Ready => SHARED_QUEUE_STATE_READY,
+            QueueState::Stopping => SHARED_QUEUE_STATE_STOPPING,
+        }
+    }
+}
+
+/// OBS's `struct queue_header` from `shared-memory-queue.h`, at the start of
+/// the `OBSVirtualCamVideo` mapping. The first three fields are `volatile`
+/// in C and shared with the consumer, hence atomics.
+#[repr(C)]
 #[derive(Debug)]
 pub struct QueueHeader {
-    pub write_idx: *mut u32,
-    pub read_idx: *mut u32,
-    pub state: *mut u32,
+    pub write_idx: AtomicU32,
+    pub read_idx: AtomicU32,
+    pub state: AtomicU32,
     pub offsets: [u32; 3],
     pub type_: u32,
     pub cx: u32,
@@ -30,131 +121,576 @@ pub struct QueueHeader {
     pub reserved: [u32; 8],
 }
 
-pub struct VideoQueue {
-    pub handel: HANDLE,
-    pub ready_to_read: bool,
-    pub header: *mut QueueHeader,
-    pub ts: [*mut u64; 3],
-    pub frame: [*mut u8; 3],
-    pub last_inc: i64,
-    pub dup_counter: i32,
-    pub is_writer: bool,
-}
-
-impl VideoQueue {
-    pub fn video_queue_create(cx: u32, cy: u32, interval: u64) -> Option<*mut Self> {
-        let mut vq = VideoQueue {
-            handel: std::ptr::null_mut(),
-            ready_to_read: false,
-            header: std::ptr::null_mut(),
-            ts: [std::ptr::null_mut(); 3],
-            frame: [std::ptr::null_mut(); 3],
-            last_inc: 0,
-            dup_counter: 0,
-            is_writer: false,
-        };
-        let frame_size: DWORD = cx * cy * 3 / 2;
-        let mut offset_frame = [3; 3];
-        let mut size = mem::size_of::<QueueHeader>() as u32;
-        size = align_size(size as usize, 32) as u32;
-        offset_frame[0] = size;
-        size = size + frame_size + FRAME_HEADER_SIZE as u32;
-        size = align_size(size as usize, 32) as u32;
-        offset_frame[1] = size;
-        size = size + frame_size + FRAME_HEADER_SIZE as u32;
-        size = align_size(size as usize, 32) as u32;
-        offset_frame[2] = size;
-        size = size + frame_size + FRAME_HEADER_SIZE as u32;
-        size = align_size(size as usize, 32) as u32;
-
-        let mut header = QueueHeader {
-            write_idx: std::ptr::null_mut(),
-            read_idx: std::ptr::null_mut(),
-            state: std::ptr::null_mut(),
-            offsets: [0; 3],
-            type_: 0,
+const _: () = {
+    assert!(mem::size_of::<QueueHeader>() == 80);
+    assert!(mem::offset_of!(QueueHeader, write_idx) == 0);
+    assert!(mem::offset_of!(QueueHeader, read_idx) == 4);
+    assert!(mem::offset_of!(QueueHeader, state) == 8);
+    assert!(mem::offset_of!(QueueHeader, offsets) == 12);
+    assert!(mem::offset_of!(QueueHeader, type_) == 24);
+    assert!(mem::offset_of!(QueueHeader, cx) == 28);
+    assert!(mem::offset_of!(QueueHeader, cy) == 32);
+    assert!(mem::offset_of!(QueueHeader, interval) == 40);
+    assert!(mem::offset_of!(QueueHeader, reserved) == 48);
+};
+
+/// An OBS video queue laid over shared memory: a [`QueueHeader`] followed
+/// by three NV12 frame slots, each behind a 32-byte frame header that starts
+/// with the frame's timestamp.
+///
+/// The rest of each frame header carries the frame's [`FrameMetadata`].
+/// Queues created here mark `reserved[0]` with [`METADATA_MAGIC`], so
+/// readers ignore those bytes when another producer owns the queue.
+///
+/// This holds the queue protocol without the Windows mapping, so the writer
+/// and reader can be exercised over any buffer.
+#[derive(Debug)]
+pub struct SharedQueue {
+    base: NonNull<u8>,
+    len: usize,
+}
+
+unsafe impl Send for SharedQueue {}
+
+impl SharedQueue {
+    /// Writes a fresh header for a `cx`×`cy` queue in the starting state.
+    ///
+    /// # Safety
+    /// `base` must be 8-byte aligned and valid for reads and writes of `len`
+    /// bytes for as long as the queue is used.
+    pub unsafe fn create(
+        base: *mut u8,
+        len: usize,
+        cx: u32,
+        cy: u32,
+        interval: u64,
+    ) -> Result<Self, Error> {
+        let (offsets, size) = queue_layout(cx, cy);
+        let base = NonNull::new(base).ok_or(Error::InvalidArgument)?;
+        if len < size {
+            return Err(Error::InvalidArgument);
+        }
+        base.cast::<QueueHeader>().as_ptr().write(QueueHeader {
+            write_idx: AtomicU32::new(0),
+            read_idx: AtomicU32::new(0),
+            state: AtomicU32::new(SHARED_QUEUE_STATE_STARTING),
+            offsets,
+            type_: SHARED_QUEUE_TYPE_VIDEO,
             cx,
             cy,
             interval,
-            reserved: [0; 8],
-        };
+            reserved: [METADATA_MAGIC, 0, 0, 0, 0, 0, 0, 0],
+        });
+        Ok(Self { base, len })
+    }
 
-        header.state = 1 as *mut u32;
-        header.cx = cx;
-        header.cy = cy;
-        header.interval = interval;
+    /// Attaches to a queue another process created.
+    ///
+    /// # Safety
+    /// `base` must be 8-byte aligned and valid for reads of `len` bytes for
+    /// as long as the queue is used.
+    pub unsafe fn open(base: *mut u8, len: usize) -> Result<Self, Error> {
+        let base = NonNull::new(base).ok_or(Error::InvalidArgument)?;
+        if len < mem::size_of::<QueueHeader>() {
+            return Err(Error::InvalidStream("OBS queue too small".to_owned()));
+        }
+        Ok(Self { base, len })
+    }
+
+    pub fn header(&self) -> &QueueHeader {
+        unsafe { self.base.cast::<QueueHeader>().as_ref() }
+    }
+
+    pub fn state(&self) -> QueueState {
+        QueueState::from_raw(self.header().state.load(Ordering::SeqCst))
+    }
+
+    /// Size of one NV12 frame, from a single read of the header's `cx` and
+    /// `cy`.
+    fn frame_size(&self) -> Result<usize, Error> {
+        let header = self.header();
+        checked_nv12_size(header.cx, header.cy).ok_or_else(corrupt_header)
+    }
+
+    /// Timestamp and frame pointers of slot `idx` holding `frame_size`
+    /// bytes, checked against the mapping since the header may come from
+    /// another process.
+    fn slot(&self, idx: usize, frame_size: usize) -> Result<(*mut u64, *mut u8), Error> {
+        let offset = self.header().offsets[idx] as usize;
+        let end = offset
+            .checked_add(FRAME_HEADER_SIZE)
+            .and_then(|n| n.checked_add(frame_size));
+        if offset < mem::size_of::<QueueHeader>()
+            || !offset.is_multiple_of(8)
+            || end.is_none_or(|end| end > self.len)
+        {
+            return Err(corrupt_header());
+        }
+        let ts = unsafe { self.base.as_ptr().add(offset) };
+        Ok((ts as *mut u64, unsafe { ts.add(FRAME_HEADER_SIZE) }))
+    }
 
-        vq.is_writer = true;
+    /// Writes an NV12 frame into the next slot, stamping its frame header
+    /// with `timestamp` (100 ns units, see [`crate::pacer::now_100ns`]).
+    ///
+    /// Mirrors OBS's `video_queue_write`: the slot is chosen from the
+    /// incremented `write_idx`, then `read_idx` and `state` are published,
+    /// moving a starting queue to ready. A closed queue takes no more
+    /// frames and returns `Error::ObsVcamNotRunning`; a frame that is not
+    /// exactly the queue's NV12 size is `Error::InvalidFrameSize`.
+    pub fn write(&mut self, frame: &[u8], timestamp: u64) -> Result<(), Error> {
+        self.write_with_metadata(frame, timestamp, None)
+    }
 
-        for i in 0..3 {
-            header.offsets[i] = offset_frame[i];
+    /// Like [`write`](Self::write), storing `metadata` in the frame header.
+    /// Payloads longer than [`OBS_MAX_PAYLOAD`](crate::metadata::OBS_MAX_PAYLOAD)
+    /// are `Error::InvalidArgument`.
+    pub fn write_with_metadata(
+        &mut self,
+        frame: &[u8],
+        timestamp: u64,
+        metadata: Option<&FrameMetadata>,
+    ) -> Result<(), Error> {
+        if self.state() == QueueState::Stopping {
+            return Err(Error::ObsVcamNotRunning);
+        }
+        let metadata = match metadata {
+            Some(metadata) => metadata.to_obs_header()?,
+            None => [0; OBS_METADATA_SIZE],
+        };
+        let frame_size = self.frame_size()?;
+        if frame.len() != frame_size {
+            return Err(Error::InvalidFrameSize);
+        }
+        let header = self.header();
+        let inc = header.write_idx.load(Ordering::SeqCst).wrapping_add(1);
+        let (ts, dst) = self.slot(inc as usize % 3, frame_size)?;
+        let header = self.header();
+        header.write_idx.store(inc, Ordering::SeqCst);
+        unsafe {
+            frame.as_ptr().copy_to_nonoverlapping(dst, frame.len());
+            ts.write_volatile(timestamp);
+            metadata
+                .as_ptr()
+                .copy_to_nonoverlapping(ts.add(1) as *mut u8, OBS_METADATA_SIZE);
         }
+        header.read_idx.store(inc, Ordering::SeqCst);
+        header
+            .state
+            .store(SHARED_QUEUE_STATE_READY, Ordering::SeqCst);
+        Ok(())
+    }
 
-        vq.handel =
-            unsafe { OpenFileMappingW(FILE_MAP_READ, 0, VIDEO_NAME.as_ptr() as *const u16) };
-        if !vq.handel.is_null() {
-            unsafe { CloseHandle(vq.handel) };
-            return None;
+    /// Marks the queue as stopping so consumers stop showing its frames, as
+    /// OBS's `video_queue_close` does for the writer.
+    pub fn close(&mut self) {
+        self.header()
+            .state
+            .store(SHARED_QUEUE_STATE_STOPPING, Ordering::SeqCst);
+    }
+
+    /// Copies out the newest frame if it is not the one `last_inc` points
+    /// at, as OBS's `video_queue_read` does. `Ok(None)` means no new frame
+    /// yet; a queue that is stopping or invalid is
+    /// `Error::ObsVcamNotRunning`.
+    pub fn read(&self, last_inc: &mut Option<u32>) -> Result<Option<Frame>, Error> {
+        match self.state() {
+            QueueState::Starting => return Ok(None),
+            QueueState::Ready => {}
+            QueueState::Invalid | QueueState::Stopping => return Err(Error::ObsVcamNotRunning),
         }
+        let header = self.header();
+        let inc = header.read_idx.load(Ordering::SeqCst);
+        if *last_inc == Some(inc) {
+            return Ok(None);
+        }
+        let frame_size = self.frame_size()?;
+        let (ts, src) = self.slot(inc as usize % 3, frame_size)?;
+        let mut data = vec![0; frame_size];
+        let mut metadata = [0; OBS_METADATA_SIZE];
+        let timestamp = unsafe {
+            src.copy_to_nonoverlapping(data.as_mut_ptr(), frame_size);
+            (ts.add(1) as *const u8)
+                .copy_to_nonoverlapping(metadata.as_mut_ptr(), OBS_METADATA_SIZE);
+            ts.read_volatile()
+        };
+        *last_inc = Some(inc);
+        Ok(Some(Frame {
+            data,
+            timestamp: Some(timestamp),
+            metadata: match header.reserved[0] {
+                METADATA_MAGIC => FrameMetadata::from_obs_header(&metadata),
+                _ => None,
+            },
+        }))
+    }
+}
+
+/// Publishes NV12 frames on the OBS virtual camera queue, so the OBS
+/// Virtual Camera DirectShow filter shows them to other applications.
+///
+/// Owns the `OBSVirtualCamVideo` mapping; dropping it marks the queue as
+/// stopping and releases the mapping.
+#[cfg(target_os = "windows")]
+#[derive(Debug)]
+pub struct ObsVideoQueue {
+    handle: HANDLE,
+    queue: SharedQueue,
+    rate: FrameRate,
+}
+
+#[cfg(target_os = "windows")]
+unsafe impl Send for ObsVideoQueue {}
 
-        vq.handel = unsafe {
+#[cfg(target_os = "windows")]
+impl ObsVideoQueue {
+    /// Creates the queue for `cx`×`cy` frames at `rate`, which the OBS
+    /// Virtual Camera filter advertises to consumers. Rates outside
+    /// [`MIN_FPS`]..=[`MAX_FPS`] are `Error::InvalidArgument`.
+    pub fn create(cx: u32, cy: u32, rate: FrameRate) -> Result<Self, Error> {
+        if cx == 0 || cy == 0 || !cx.is_multiple_of(2) || !cy.is_multiple_of(2) {
+            return Err(Error::InvalidArgument);
+        }
+        let (num, den) = (rate.numerator() as u64, rate.denominator() as u64);
+        if num < MIN_FPS as u64 * den || num > MAX_FPS as u64 * den {
+            return Err(Error::InvalidArgument);
+        }
+        let interval = rate.interval();
+        let (_, size) = queue_layout(cx, cy);
+        let name = WideString::new(VIDEO_NAME)?;
+        let existing = unsafe { OpenFileMappingW(FILE_MAP_READ, 0, name.as_ptr()) };
+        if !existing.is_null() {
+            unsafe { CloseHandle(existing) };
+            debug!("OBS virtual camera queue is owned by another producer");
+            return Err(Error::ObsVcamInUse);
+        }
+        let handle = unsafe {
             CreateFileMappingW(
                 INVALID_HANDLE_VALUE,
-                std::ptr::null_mut(),
+                ptr::null_mut(),
                 PAGE_READWRITE,
-                0,
-                size,
-                VIDEO_NAME.as_ptr() as *const u16,
+                (size as u64 >> 32) as u32,
+                size as u32,
+                name.as_ptr(),
             )
         };
-        if vq.handel.is_null() {
-            return None;
+        if handle.is_null() {
+            return Err(Error::Io(std::io::Error::last_os_error()));
+        }
+        // lost a race with another producer
+        if std::io::Error::last_os_error().raw_os_error() == Some(ERROR_ALREADY_EXISTS as i32) {
+            unsafe { CloseHandle(handle) };
+            debug!("lost the OBS virtual camera queue to another producer");
+            return Err(Error::ObsVcamInUse);
+        }
+        let view = unsafe { MapViewOfFile(handle, FILE_MAP_ALL_ACCESS, 0, 0, 0) };
+        if view.is_null() {
+            let e = std::io::Error::last_os_error();
+            unsafe { CloseHandle(handle) };
+            return Err(Error::Io(e));
+        }
+        match unsafe { SharedQueue::create(view as *mut u8, size, cx, cy, interval) } {
+            Ok(queue) => {
+                info!(cx, cy, interval, size; "created OBS virtual camera queue");
+                Ok(Self {
+                    handle,
+                    queue,
+                    rate,
+                })
+            }
+            Err(e) => {
+                unsafe {
+                    UnmapViewOfFile(view);
+                    CloseHandle(handle);
+                }
+                Err(e)
+            }
         }
+    }
 
-        vq.header =
-            unsafe { MapViewOfFile(vq.handel, FILE_MAP_ALL_ACCESS, 0, 0, 0) as *mut QueueHeader };
-        if vq.header.is_null() {
-            unsafe { CloseHandle(vq.handel) };
-            return None;
+    /// Like [`create`](Self::create), but while another producer owns the
+    /// queue, retries until it is released or `timeout` has passed.
+    pub fn create_when_free(
+        cx: u32,
+        cy: u32,
+        rate: FrameRate,
+        timeout: Duration,
+    ) -> Result<Self, Error> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            match Self::create(cx, cy, rate) {
+                Err(Error::ObsVcamInUse) if Instant::now() < deadline => {
+                    std::thread::sleep(IN_USE_POLL.min(deadline - Instant::now()))
+                }
+                result => return result,
+            }
         }
-        // 将header拷贝到共享内存中
-        let header_size = mem::size_of::<QueueHeader>();
+    }
+
+    pub fn width(&self) -> u32 {
+        self.queue.header().cx
+    }
+
+    pub fn height(&self) -> u32 {
+        self.queue.header().cy
+    }
+
+    /// Frame interval in 100 ns units.
+    pub fn interval(&self) -> u64 {
+        self.queue.header().interval
+    }
+
+    /// The rate the queue was created with; consumers see it rounded to
+    /// [`interval`](Self::interval).
+    pub fn frame_rate(&self) -> FrameRate {
+        self.rate
+    }
+
+    pub fn state(&self) -> QueueState {
+        self.queue.state()
+    }
+
+    /// Writes one NV12 frame of exactly `width`×`height`.
+    pub fn write(&mut self, frame: &[u8], timestamp: u64) -> Result<(), Error> {
+        self.write_with_metadata(frame, timestamp, None)
+    }
+
+    pub fn write_with_metadata(
+        &mut self,
+        frame: &[u8],
+        timestamp: u64,
+        metadata: Option<&FrameMetadata>,
+    ) -> Result<(), Error> {
+        self.queue.write_with_metadata(frame, timestamp, metadata)
+    }
+
+    /// Marks the queue as stopping; later writes fail. The mapping stays
+    /// until the queue is dropped.
+    pub fn close(&mut self) {
+        debug!("closing OBS virtual camera queue");
+        self.queue.close();
+    }
+}
+
+#[cfg(target_os = "windows")]
+impl Drop for ObsVideoQueue {
+    fn drop(&mut self) {
+        self.queue.close();
         unsafe {
-            (&header as *const QueueHeader).copy_to_nonoverlapping(vq.header, header_size);
+            UnmapViewOfFile(self.queue.base.as_ptr() as *mut _);
+            CloseHandle(self.handle);
         }
-        println!("header: {:?}", vq.header);
+    }
+}
+
+/// State of the OBS virtual camera queue if another process, a producer or
+/// a consumer, still has it mapped; `None` if it is free.
+///
+/// `Some(QueueState::Stopping)` usually means the producer closed the queue
+/// and a consumer has yet to let go of it; creating a queue fails with
+/// `Error::ObsVcamInUse` until it does.
+#[cfg(target_os = "windows")]
+pub fn probe() -> Option<QueueState> {
+    ObsVcamReader::open().ok().map(|reader| reader.state())
+}
 
-        for i in 0..3 {
-            let off = offset_frame[i] as u32;
-            let ts_ptr = unsafe { vq.header.offset(off as isize) } as *mut u64;
-            vq.ts[i] = ts_ptr;
-            let frame_ptr =
-                unsafe { vq.header.offset((off + FRAME_HEADER_SIZE) as isize) } as *mut u8;
-            vq.frame[i] = frame_ptr;
+/// Reads the frames OBS Studio, or any other producer, publishes on the OBS
+/// virtual camera queue.
+#[cfg(target_os = "windows")]
+pub struct ObsVcamReader {
+    handle: HANDLE,
+    queue: SharedQueue,
+    last_inc: Option<u32>,
+}
+
+#[cfg(target_os = "windows")]
+impl ObsVcamReader {
+    /// Opens the existing `OBSVirtualCamVideo` mapping;
+    /// `Error::ObsVcamNotRunning` if nobody is publishing.
+    pub fn open() -> Result<Self, Error> {
+        let name = WideString::new(VIDEO_NAME)?;
+        let handle = unsafe { OpenFileMappingW(FILE_MAP_READ, 0, name.as_ptr()) };
+        if handle.is_null() {
+            return Err(Error::ObsVcamNotRunning);
         }
-        let layout = Layout::new::<VideoQueue>();
-        let pvq = unsafe { alloc(layout) as *mut VideoQueue };
-        if pvq.is_null() {
-            unsafe { CloseHandle(vq.handel) };
-            return None;
+        let view = unsafe { MapViewOfFile(handle, FILE_MAP_READ, 0, 0, 0) };
+        if view.is_null() {
+            let e = std::io::Error::last_os_error();
+            unsafe { CloseHandle(handle) };
+            return Err(Error::Io(e));
         }
-        unsafe {
-            ptr::copy_nonoverlapping(&vq as *const VideoQueue, pvq, mem::size_of::<VideoQueue>());
+        let mut info: MEMORY_BASIC_INFORMATION = unsafe { mem::zeroed() };
+        let len = match unsafe { VirtualQuery(view, &mut info, mem::size_of_val(&info)) } {
+            0 => 0,
+            _ => info.RegionSize,
+        };
+        match unsafe { SharedQueue::open(view as *mut u8, len) } {
+            Ok(queue) => {
+                debug!(len; "opened OBS virtual camera queue for reading");
+                Ok(Self {
+                    handle,
+                    queue,
+                    last_inc: None,
+                })
+            }
+            Err(e) => {
+                unsafe {
+                    UnmapViewOfFile(view);
+                    CloseHandle(handle);
+                }
+                Err(e)
+            }
         }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.queue.header().cx
+    }
+
+    pub fn height(&self) -> u32 {
+        self.queue.header().cy
+    }
+
+    /// Frame interval the producer advertises, in 100 ns units.
+    pub fn interval(&self) -> u64 {
+        self.queue.header().interval
+    }
+
+    /// The rate [`interval`](Self::interval) encodes.
+    pub fn frame_rate(&self) -> Result<FrameRate, Error> {
+        FrameRate::from_interval(self.interval())
+    }
+
+    pub fn state(&self) -> QueueState {
+        self.queue.state()
+    }
+
+    /// The newest NV12 frame with the metadata its producer attached, or
+    /// `None` if there is none since the last call. Fails with `Error::ObsVcamNotRunning` once the producer stops.
+    pub fn read(&mut self) -> Result<Option<Frame>, Error> {
+        self.queue.read(&mut self.last_inc)
+    }
+}
 
-        Some(pvq)
+#[cfg(target_os = "windows")]
+impl Drop for ObsVcamReader {
+    fn drop(&mut self) {
+        unsafe {
+            UnmapViewOfFile(self.queue.base.as_ptr() as *mut _);
+            CloseHandle(self.handle);
+        }
     }
 }
 
+#[cfg(target_os = "windows")]
 #[test]
 fn test_video_queue_create() {
-    let interval = (10000000.0 / 25.0) as u64;
-    let pvq = VideoQueue::video_queue_create(1280, 720, interval);
-    // assert!(pvq.is_some());
-    println!("pvq: {:?}", pvq);
-    let pvq = pvq.unwrap();
-    unsafe {
-        let header = &*(*pvq).header;
-        println!("header: {:?}", header);
+    let rate = FrameRate::new(30000, 1001).unwrap();
+    let mut queue = ObsVideoQueue::create(1280, 720, rate).unwrap();
+    assert_eq!((queue.width(), queue.height()), (1280, 720));
+    assert_eq!(queue.interval(), 333_667);
+    assert_eq!(queue.frame_rate(), rate);
+    assert_eq!(queue.state(), QueueState::Starting);
+    queue.write(&vec![0; 1280 * 720 * 3 / 2], 0).unwrap();
+    assert_eq!(queue.state(), QueueState::Ready);
+    assert!(matches!(
+        ObsVideoQueue::create(1280, 720, rate),
+        Err(Error::ObsVcamInUse)
+    ));
+    assert_eq!(probe(), Some(QueueState::Ready));
+    drop(queue);
+    assert_eq!(probe(), None);
+}
+
+#[test]
+fn test_shared_queue_loopback() {
+    let (_, size) = queue_layout(4, 2);
+    let mut buf = vec![0u64; size.div_ceil(8)];
+    let base = buf.as_mut_ptr() as *mut u8;
+    let mut writer = unsafe { SharedQueue::create(base, size, 4, 2, 333_333) }.unwrap();
+    let reader = unsafe { SharedQueue::open(base, size) }.unwrap();
+    let mut last_inc = None;
+
+    assert_eq!(reader.header().offsets, [96, 160, 224]);
+    assert_eq!(reader.state(), QueueState::Starting);
+    assert!(reader.read(&mut last_inc).unwrap().is_none());
+
+    let metadata = FrameMetadata::new(41, 90).payload(b"cam1".to_vec());
+    writer
+        .write_with_metadata(&[1; 12], 100, Some(&metadata))
+        .unwrap();
+    let frame = reader.read(&mut last_inc).unwrap().unwrap();
+    assert_eq!((frame.data, frame.timestamp), (vec![1; 12], Some(100)));
+    assert_eq!(frame.metadata, Some(metadata));
+    assert!(reader.read(&mut last_inc).unwrap().is_none());
+
+    // a slow reader only sees the newest frame
+    for i in 2..6 {
+        writer.write(&[i; 12], i as u64 * 100).unwrap();
+    }
+    let frame = reader.read(&mut last_inc).unwrap().unwrap();
+    assert_eq!((frame.data, frame.timestamp), (vec![5; 12], Some(500)));
+    assert_eq!(frame.metadata, None);
+
+    // frames of the wrong size are rejected without touching the queue
+    for len in [11, 13] {
+        assert!(matches!(
+            writer.write(&vec![9; len], 600),
+            Err(Error::InvalidFrameSize)
+        ));
+    }
+    assert!(reader.read(&mut last_inc).unwrap().is_none());
+
+    writer.close();
+    assert!(matches!(
+        reader.read(&mut last_inc),
+        Err(Error::ObsVcamNotRunning)
+    ));
+}
+
+#[test]
+fn test_shared_queue_state_transitions() {
+    let (_, size) = queue_layout(2, 2);
+    let mut buf = vec![0u64; size.div_ceil(8)];
+    let base = buf.as_mut_ptr() as *mut u8;
+    let state = |buf: &[u64]| QueueState::from_raw(buf[1] as u32);
+
+    assert_eq!(state(&buf), QueueState::Invalid);
+    let mut writer = unsafe { SharedQueue::create(base, size, 2, 2, 333_333) }.unwrap();
+    assert_eq!(state(&buf), QueueState::Starting);
+    writer.write(&[0; 6], 1).unwrap();
+    assert_eq!(state(&buf), QueueState::Ready);
+    writer.write(&[0; 6], 2).unwrap();
+    assert_eq!(state(&buf), QueueState::Ready);
+    writer.close();
+    assert_eq!(state(&buf), QueueState::Stopping);
+    assert!(matches!(
+        writer.write(&[0; 6], 3),
+        Err(Error::ObsVcamNotRunning)
+    ));
+    assert_eq!(state(&buf), QueueState::Stopping);
+    assert_eq!(writer.header().read_idx.load(Ordering::SeqCst), 2);
+}
+
+#[test]
+fn test_shared_queue_corrupt_size() {
+    let (_, size) = queue_layout(2, 2);
+    let mut buf = vec![0u64; size.div_ceil(8)];
+    let base = buf.as_mut_ptr() as *mut u8;
+    let mut writer = unsafe { SharedQueue::create(base, size, 2, 2, 333_333) }.unwrap();
+    let reader = unsafe { SharedQueue::open(base, size) }.unwrap();
+    writer.write(&[0; 6], 1).unwrap();
+
+    // one size overflows, the other runs past the mapping
+    for (cx, cy) in [(u32::MAX, u32::MAX), (65_536, 65_536)] {
+        unsafe {
+            (base.add(mem::offset_of!(QueueHeader, cx)) as *mut u32).write(cx);
+            (base.add(mem::offset_of!(QueueHeader, cy)) as *mut u32).write(cy);
+        }
+        assert!(matches!(
+            reader.read(&mut None),
+            Err(Error::InvalidStream(_))
+        ));
+        assert!(writer.write(&[0; 6], 2).is_err());
     }
 }
@@ -0,0 +1,389 @@
+use std::fmt;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+use crate::Error;
+
+/// 100 ns ticks per second, the unit OBS uses for frame timestamps and intervals.
+pub const TICKS_PER_SECOND: u64 = 10_000_000;
+/// Late frames dropped in a row before the pacer gives up on catching up
+/// and re-anchors.
+const MAX_LATE_DROPS: u32 = 3;
+
+/// Current monotonic time in 100 ns units.
+///
+/// On Windows this is derived from `QueryPerformanceCounter`, the same clock
+/// OBS stamps its virtual camera frames with, so timestamps are comparable
+/// across processes.
+#[cfg(target_os = "windows")]
+pub fn now_100ns() -> u64 {
+    use winapi::um::profileapi::{QueryPerformanceCounter, QueryPerformanceFrequency};
+    use winapi::um::winnt::LARGE_INTEGER;
+
+    let mut freq: LARGE_INTEGER = unsafe { std::mem::zeroed() };
+    let mut count: LARGE_INTEGER = unsafe { std::mem::zeroed() };
+    unsafe {
+        QueryPerformanceFrequency(&mut freq);
+        QueryPerformanceCounter(&mut count);
+    }
+    let freq = unsafe { *freq.QuadPart() } as u128;
+    let count = unsafe { *count.QuadPart() } as u128;
+    (count * TICKS_PER_SECOND as u128 / freq) as u64
+}
+
+/// Current monotonic time in 100 ns units, relative to the first call.
+#[cfg(not(target_os = "windows"))]
+pub fn now_100ns() -> u64 {
+    use std::sync::OnceLock;
+
+    static ORIGIN: OnceLock<Instant> = OnceLock::new();
+    let origin = *ORIGIN.get_or_init(Instant::now);
+    (origin.elapsed().as_nanos() / 100) as u64
+}
+
+/// A frame rate as a fraction of frames per second, e.g. 30000/1001 for
+/// NTSC's 29.97. Both parts are non-zero and in lowest terms.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FrameRate {
+    numerator: u32,
+    denominator: u32,
+}
+
+impl FrameRate {
+    /// `fps` whole frames per second, for constants.
+    ///
+    /// # Panics
+    /// If `fps` is zero, at compile time in a `const`.
+    pub const fn from_integer(fps: u32) -> Self {
+        assert!(fps > 0, "frame rate must be positive");
+        Self {
+            numerator: fps,
+            denominator: 1,
+        }
+    }
+
+    pub fn new(numerator: u32, denominator: u32) -> Result<Self, Error> {
+        if numerator == 0 || denominator == 0 {
+            return Err(Error::InvalidArgument);
+        }
+        let gcd = gcd(numerator as u64, denominator as u64) as u32;
+        Ok(Self {
+            numerator: numerator / gcd,
+            denominator: denominator / gcd,
+        })
+    }
+
+    /// The closest rate to `fps`, recognising the NTSC x/1.001 rates.
+    pub fn from_fps(fps: f64) -> Result<Self, Error> {
+        if fps.is_nan() || fps <= 0.0 || fps > u32::MAX as f64 / 1000.0 {
+            return Err(Error::InvalidArgument);
+        }
+        let ntsc = (fps * 1.001).round();
+        match (
+            (fps - fps.round()).abs() < 1e-6,
+            (fps * 1.001 - ntsc).abs() < 1e-3,
+        ) {
+            (true, _) => Self::new(fps.round() as u32, 1),
+            (false, true) => Self::new(
+                (ntsc as u32)
+                    .checked_mul(1000)
+                    .ok_or(Error::InvalidArgument)?,
+                1001,
+            ),
+            _ => Self::new((fps * 1000.0).round() as u32, 1000),
+        }
+    }
+
+    /// The rate a frame interval in 100 ns units encodes.
+    pub fn from_interval(interval: u64) -> Result<Self, Error> {
+        match u32::try_from(interval) {
+            Ok(interval) if interval > 0 => Self::new(TICKS_PER_SECOND as u32, interval),
+            _ => Err(Error::InvalidArgument),
+        }
+    }
+
+    /// Frame interval in 100 ns units, rounded to the nearest tick.
+    pub fn interval(self) -> u64 {
+        let (num, den) = (self.numerator as u64, self.denominator as u64);
+        (TICKS_PER_SECOND * den + num / 2) / num
+    }
+
+    pub const fn numerator(self) -> u32 {
+        self.numerator
+    }
+
+    pub const fn denominator(self) -> u32 {
+        self.denominator
+    }
+
+    pub fn fps(self) -> f64 {
+        self.numerator as f64 / self.denominator as f64
+    }
+}
+
+impl fmt::Display for FrameRate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.denominator {
+            1 => write!(f, "{}", self.numerator),
+            den => write!(f, "{}/{}", self.numerator, den),
+        }
+    }
+}
+
+/// Parses `30`, `29.97` or `30000/1001`.
+impl FromStr for FrameRate {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Error> {
+        match s.split_once('/') {
+            Some((num, den)) => Self::new(
+                num.trim().parse().map_err(|_| Error::InvalidArgument)?,
+                den.trim().parse().map_err(|_| Error::InvalidArgument)?,
+            ),
+            None => Self::from_fps(s.trim().parse().map_err(|_| Error::InvalidArgument)?),
+        }
+    }
+}
+
+fn gcd(a: u64, b: u64) -> u64 {
+    match b {
+        0 => a,
+        b => gcd(b, a % b),
+    }
+}
+
+fn ticks_to_duration(ticks: u64) -> Duration {
+    Duration::from_nanos(ticks.saturating_mul(100))
+}
+
+/// What the pacer wants done with a frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pace {
+    /// The frame is due now.
+    Send,
+    /// The frame is early; wait this long before sending it.
+    Hold(Duration),
+    /// The frame is too late, or arrives before the next frame slot.
+    Drop,
+}
+
+/// Maps presentation timestamps onto wall-clock time.
+///
+/// The first frame anchors its timestamp to the moment it was paced. Later
+/// frames are held until their timestamp is due, and dropped when they are
+/// more than `max_lateness` behind or closer than half an interval to the
+/// previously sent frame. A timestamp jump backwards, or further ahead than
+/// `max_hold`, re-anchors the clock instead of stalling the caller, and so
+/// does a run of late frames, as from a real-time producer that stalled and
+/// will never catch up.
+#[derive(Debug, Clone)]
+pub struct Pacer {
+    interval: u64,
+    max_lateness: Duration,
+    max_hold: Duration,
+    anchor: Option<(u64, Instant)>,
+    last_sent: Option<u64>,
+    late_drops: u32,
+}
+
+impl Pacer {
+    /// Creates a pacer for frames `interval` 100 ns units apart.
+    pub fn new(interval: u64) -> Self {
+        let period = ticks_to_duration(interval);
+        Self {
+            interval,
+            max_lateness: period * 2,
+            max_hold: Duration::from_secs(1).max(period * 2),
+            anchor: None,
+            last_sent: None,
+            late_drops: 0,
+        }
+    }
+
+    /// A pacer for `fps`; rates [`FrameRate::from_fps`] rejects are
+    /// `Error::InvalidArgument`.
+    pub fn from_fps(fps: f64) -> Result<Self, Error> {
+        Ok(Self::new(FrameRate::from_fps(fps)?.interval()))
+    }
+
+    /// How late a frame may be before it is dropped.
+    pub fn max_lateness(mut self, max_lateness: Duration) -> Self {
+        self.max_lateness = max_lateness;
+        self
+    }
+
+    /// How far ahead a frame may be before the pacer re-anchors.
+    pub fn max_hold(mut self, max_hold: Duration) -> Self {
+        self.max_hold = max_hold;
+        self
+    }
+
+    /// Frame interval in 100 ns units.
+    pub fn interval(&self) -> u64 {
+        self.interval
+    }
+
+    /// Forgets the current anchor; the next frame is sent immediately.
+    pub fn reset(&mut self) {
+        self.anchor = None;
+        self.last_sent = None;
+        self.late_drops = 0;
+    }
+
+    pub fn pace(&mut self, timestamp: u64) -> Pace {
+        self.pace_at(timestamp, Instant::now())
+    }
+
+    fn pace_at(&mut self, timestamp: u64, now: Instant) -> Pace {
+        if let Some(last) = self.last_sent {
+            if timestamp >= last && timestamp - last < self.interval / 2 {
+                return Pace::Drop;
+            }
+        }
+        let (anchor_ts, anchor_at) = match self.anchor {
+            Some((anchor_ts, anchor_at)) if timestamp >= anchor_ts => (anchor_ts, anchor_at),
+            _ => return self.reanchor(timestamp, now),
+        };
+        let due = anchor_at + ticks_to_duration(timestamp - anchor_ts);
+        if due > now {
+            let wait = due - now;
+            if wait > self.max_hold {
+                return self.reanchor(timestamp, now);
+            }
+            self.late_drops = 0;
+            self.last_sent = Some(timestamp);
+            Pace::Hold(wait)
+        } else if now - due > self.max_lateness {
+            if self.late_drops >= MAX_LATE_DROPS {
+                return self.reanchor(timestamp, now);
+            }
+            self.late_drops += 1;
+            Pace::Drop
+        } else {
+            self.late_drops = 0;
+            self.last_sent = Some(timestamp);
+            Pace::Send
+        }
+    }
+
+    fn reanchor(&mut self, timestamp: u64, now: Instant) -> Pace {
+        self.anchor = Some((timestamp, now));
+        self.last_sent = Some(timestamp);
+        self.late_drops = 0;
+        Pace::Send
+    }
+}
+
+/// Sleeps callers onto a fixed frame grid, for loops that render on demand
+/// instead of stamping frames.
+#[derive(Debug, Clone)]
+pub struct FrameClock {
+    period: Duration,
+    next: Option<Instant>,
+}
+
+impl FrameClock {
+    pub fn new(rate: FrameRate) -> Self {
+        Self {
+            period: ticks_to_duration(rate.interval()),
+            next: None,
+        }
+    }
+
+    /// A clock for `fps`; rates [`FrameRate::from_fps`] rejects are
+    /// `Error::InvalidArgument`.
+    pub fn from_fps(fps: f64) -> Result<Self, Error> {
+        Ok(Self::new(FrameRate::from_fps(fps)?))
+    }
+
+    pub fn period(&self) -> Duration {
+        self.period
+    }
+
+    /// Sleeps until the next slot on a grid that starts at the first call. A
+    /// caller that fell more than a period behind restarts the grid from now
+    /// rather than rushing to catch up.
+    pub fn sleep_until_next_frame(&mut self) {
+        let now = Instant::now();
+        let next = match self.next {
+            Some(next) if next + self.period > now => next,
+            _ => now,
+        };
+        if next > now {
+            std::thread::sleep(next - now);
+        }
+        self.next = Some(next + self.period);
+    }
+}
+
+#[test]
+fn test_pacer_hold_and_drop() {
+    let interval = TICKS_PER_SECOND / 25;
+    let mut pacer = Pacer::new(interval);
+    let start = Instant::now();
+    assert_eq!(pacer.pace_at(1_000, start), Pace::Send);
+    // Next frame is due one interval later.
+    assert_eq!(
+        pacer.pace_at(1_000 + interval, start),
+        Pace::Hold(Duration::from_millis(40))
+    );
+    // A frame squeezed in before the next slot is dropped.
+    assert_eq!(pacer.pace_at(1_000 + interval + 10, start), Pace::Drop);
+    // Far behind the wall clock: dropped as late.
+    let later = start + Duration::from_secs(1);
+    assert_eq!(pacer.pace_at(1_000 + interval * 2, later), Pace::Drop);
+    // Jumping backwards re-anchors.
+    assert_eq!(pacer.pace_at(0, later), Pace::Send);
+}
+
+#[test]
+fn test_pacer_recovers_from_stall() {
+    let interval = TICKS_PER_SECOND / 25;
+    let period = Duration::from_millis(40);
+    let mut pacer = Pacer::new(interval);
+    let start = Instant::now();
+    for i in 0..5 {
+        assert_eq!(
+            pacer.pace_at(i * interval, start + period * i as u32),
+            Pace::Send
+        );
+    }
+    // a 300 ms stall, then frames at the normal rate again
+    let paced: Vec<Pace> = (5..20)
+        .map(|i| {
+            let now = start + period * i as u32 + Duration::from_millis(300);
+            pacer.pace_at(i * interval, now)
+        })
+        .collect();
+    let drops = MAX_LATE_DROPS as usize;
+    assert_eq!(paced[..drops], vec![Pace::Drop; drops]);
+    assert!(paced[drops..].iter().all(|&pace| pace == Pace::Send));
+}
+
+#[test]
+fn test_frame_rate() {
+    let ntsc: FrameRate = "30000/1001".parse().unwrap();
+    assert_eq!(ntsc.interval(), 333_667);
+    assert_eq!(FrameRate::from_fps(29.97).unwrap(), ntsc);
+    assert_eq!("29.97".parse::<FrameRate>().unwrap(), ntsc);
+    assert_eq!(ntsc.to_string(), "30000/1001");
+
+    let pal = FrameRate::new(50, 2).unwrap();
+    assert_eq!((pal.numerator(), pal.denominator()), (25, 1));
+    assert_eq!(FrameRate::from_integer(25), pal);
+    assert_eq!(pal.interval(), 400_000);
+    assert_eq!(FrameRate::from_interval(400_000).unwrap(), pal);
+    assert_eq!(FrameRate::from_fps(12.5).unwrap().to_string(), "25/2");
+
+    assert!(FrameRate::new(30, 0).is_err());
+    assert!("0".parse::<FrameRate>().is_err());
+    assert!(FrameRate::from_interval(0).is_err());
+    // NTSC-like, just under the limit, with a numerator past u32::MAX
+    assert!("4290677.3223".parse::<FrameRate>().is_err());
+
+    assert_eq!(
+        FrameClock::new(ntsc).period(),
+        Duration::from_nanos(33_366_700)
+    );
+    assert!(FrameClock::from_fps(1e-300).is_err());
+}
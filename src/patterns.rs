@@ -0,0 +1,327 @@
+use std::str::FromStr;
+
+use crate::format::{convert, PixelFormat};
+use crate::pacer::{now_100ns, FrameRate, Pace, Pacer, TICKS_PER_SECOND};
+use crate::{Camera, Error, Frame};
+
+/// Known-good test signals.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pattern {
+    /// SMPTE ECR 1-1978 colour bars.
+    ColorBars,
+    /// Black and white squares, eight rows high.
+    Checkerboard,
+    /// A colour ramp scrolling one step per frame, so frozen output is obvious.
+    Gradient,
+    /// Frame counter and clock on a dark background.
+    Counter,
+    Solid([u8; 3]),
+}
+
+impl FromStr for Pattern {
+    type Err = Error;
+
+    /// Parses `bars`, `checkerboard`, `gradient`, `counter`, or `solid` with
+    /// an optional `:RRGGBB` colour (mid grey by default).
+    fn from_str(s: &str) -> Result<Self, Error> {
+        let (name, arg) = match s.split_once(':') {
+            Some((name, arg)) => (name, Some(arg)),
+            None => (s, None),
+        };
+        match (name.to_ascii_lowercase().as_str(), arg) {
+            ("bars" | "colorbars", None) => Ok(Pattern::ColorBars),
+            ("checkerboard" | "checker", None) => Ok(Pattern::Checkerboard),
+            ("gradient", None) => Ok(Pattern::Gradient),
+            ("counter" | "clock", None) => Ok(Pattern::Counter),
+            ("solid", None) => Ok(Pattern::Solid([128, 128, 128])),
+            ("solid", Some(hex)) if hex.len() == 6 => {
+                let rgb = u32::from_str_radix(hex, 16).map_err(|_| Error::InvalidArgument)?;
+                Ok(Pattern::Solid([
+                    (rgb >> 16) as u8,
+                    (rgb >> 8) as u8,
+                    rgb as u8,
+                ]))
+            }
+            _ => Err(Error::InvalidArgument),
+        }
+    }
+}
+
+/// Renders a [`Pattern`] at a fixed size and pixel format.
+#[derive(Debug, Clone)]
+pub struct PatternGenerator {
+    pattern: Pattern,
+    width: u32,
+    height: u32,
+    format: PixelFormat,
+    overlay: bool,
+}
+
+impl PatternGenerator {
+    pub fn new(pattern: Pattern, width: u32, height: u32, format: PixelFormat) -> Self {
+        Self {
+            pattern,
+            width,
+            height,
+            format,
+            overlay: pattern == Pattern::Counter,
+        }
+    }
+
+    /// Matches the camera's size and format.
+    pub fn for_camera(pattern: Pattern, camera: &Camera) -> Self {
+        Self::new(pattern, camera.width(), camera.height(), camera.format())
+    }
+
+    /// Burns the frame counter and clock into any pattern.
+    pub fn overlay(mut self, overlay: bool) -> Self {
+        self.overlay = overlay;
+        self
+    }
+
+    /// Renders frame `index`; `elapsed` (100 ns units) drives the clock overlay.
+    pub fn render(&self, index: u64, elapsed: u64) -> Vec<u8> {
+        let rgba = self.render_rgba(index, elapsed);
+        convert(
+            &rgba,
+            PixelFormat::Rgba,
+            self.format,
+            self.width,
+            self.height,
+        )
+        .expect("rendered frame has the generator's size")
+    }
+
+    fn render_rgba(&self, index: u64, elapsed: u64) -> Vec<u8> {
+        let (w, h) = (self.width as usize, self.height as usize);
+        let mut rgba = vec![255; w * h * 4];
+        for y in 0..h {
+            for x in 0..w {
+                let [r, g, b] = self.pixel(x, y, index);
+                rgba[(y * w + x) * 4..][..3].copy_from_slice(&[r, g, b]);
+            }
+        }
+        if self.overlay {
+            let secs = elapsed / TICKS_PER_SECOND;
+            let millis = elapsed % TICKS_PER_SECOND / 10_000;
+            let text = format!(
+                "{:06} {:02}:{:02}:{:02}.{:03}",
+                index,
+                secs / 3600,
+                secs / 60 % 60,
+                secs % 60,
+                millis
+            );
+            draw_text(&mut rgba, w, h, &text);
+        }
+        rgba
+    }
+
+    fn pixel(&self, x: usize, y: usize, index: u64) -> [u8; 3] {
+        let (w, h) = (self.width as usize, self.height as usize);
+        match self.pattern {
+            Pattern::ColorBars => color_bar(x, y, w, h),
+            Pattern::Checkerboard => {
+                let size = (h / 8).max(1);
+                match (x / size + y / size) % 2 {
+                    0 => [255, 255, 255],
+                    _ => [0, 0, 0],
+                }
+            }
+            Pattern::Gradient => {
+                let r = ((x * 256 / w.max(1)) as u64 + index * 4) % 256;
+                let g = y * 255 / h.saturating_sub(1).max(1);
+                [r as u8, g as u8, 255 - r as u8]
+            }
+            Pattern::Counter => [16, 16, 16],
+            Pattern::Solid(rgb) => rgb,
+        }
+    }
+}
+
+fn color_bar(x: usize, y: usize, w: usize, h: usize) -> [u8; 3] {
+    const TOP: [[u8; 3]; 7] = [
+        [191, 191, 191],
+        [191, 191, 0],
+        [0, 191, 191],
+        [0, 191, 0],
+        [191, 0, 191],
+        [191, 0, 0],
+        [0, 0, 191],
+    ];
+    const MIDDLE: [[u8; 3]; 7] = [
+        [0, 0, 191],
+        [19, 19, 19],
+        [191, 0, 191],
+        [19, 19, 19],
+        [0, 191, 191],
+        [19, 19, 19],
+        [191, 191, 191],
+    ];
+    // -I, white, +Q, black, then the PLUGE steps and black
+    const BOTTOM: [([u8; 3], usize); 8] = [
+        ([0, 33, 76], 15),
+        ([255, 255, 255], 15),
+        ([50, 0, 106], 15),
+        ([19, 19, 19], 15),
+        ([9, 9, 9], 4),
+        ([19, 19, 19], 4),
+        ([29, 29, 29], 4),
+        ([19, 19, 19], 12),
+    ];
+    // positions in 84ths of the width, 12 per top bar
+    let pos = x * 84 / w;
+    if y < h * 2 / 3 {
+        TOP[pos / 12]
+    } else if y < h * 3 / 4 {
+        MIDDLE[pos / 12]
+    } else {
+        let mut end = 0;
+        for (rgb, span) in BOTTOM {
+            end += span;
+            if pos < end {
+                return rgb;
+            }
+        }
+        BOTTOM[7].0
+    }
+}
+
+const GLYPH_WIDTH: usize = 3;
+const GLYPH_HEIGHT: usize = 5;
+
+/// 3x5 bitmaps, one row per entry, most significant bit on the left.
+fn glyph(c: char) -> [u8; GLYPH_HEIGHT] {
+    match c {
+        '0' => [0b111, 0b101, 0b101, 0b101, 0b111],
+        '1' => [0b010, 0b110, 0b010, 0b010, 0b111],
+        '2' => [0b111, 0b001, 0b111, 0b100, 0b111],
+        '3' => [0b111, 0b001, 0b111, 0b001, 0b111],
+        '4' => [0b101, 0b101, 0b111, 0b001, 0b001],
+        '5' => [0b111, 0b100, 0b111, 0b001, 0b111],
+        '6' => [0b111, 0b100, 0b111, 0b101, 0b111],
+        '7' => [0b111, 0b001, 0b001, 0b001, 0b001],
+        '8' => [0b111, 0b101, 0b111, 0b101, 0b111],
+        '9' => [0b111, 0b101, 0b111, 0b001, 0b111],
+        ':' => [0b000, 0b010, 0b000, 0b010, 0b000],
+        '.' => [0b000, 0b000, 0b000, 0b000, 0b010],
+        _ => [0; GLYPH_HEIGHT],
+    }
+}
+
+/// Draws white text on a black box in the top-left corner, clipped to the frame.
+fn draw_text(rgba: &mut [u8], w: usize, h: usize, text: &str) {
+    let scale = (h / 120).max(1);
+    let advance = (GLYPH_WIDTH + 1) * scale;
+    let box_w = (text.chars().count() * advance + scale).min(w);
+    let box_h = ((GLYPH_HEIGHT + 2) * scale).min(h);
+    let mut put = |x: usize, y: usize, value: u8| {
+        if x < w && y < h {
+            rgba[(y * w + x) * 4..][..3].fill(value);
+        }
+    };
+    for y in 0..box_h {
+        for x in 0..box_w {
+            put(x, y, 0);
+        }
+    }
+    for (i, c) in text.chars().enumerate() {
+        for (row, bits) in glyph(c).iter().enumerate() {
+            for col in 0..GLYPH_WIDTH {
+                if bits & (1 << (GLYPH_WIDTH - 1 - col)) == 0 {
+                    continue;
+                }
+                for dy in 0..scale {
+                    for dx in 0..scale {
+                        put(
+                            scale + i * advance + col * scale + dx,
+                            scale + row * scale + dy,
+                            255,
+                        );
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Streams `pattern` to `camera` at `rate` until `frames` have been sent, or
+/// forever when `frames` is `None`. Frames the consumer skips are not errors.
+pub fn stream(
+    camera: &mut Camera,
+    pattern: Pattern,
+    rate: FrameRate,
+    frames: Option<u64>,
+) -> Result<(), Error> {
+    let generator = PatternGenerator::for_camera(pattern, camera);
+    let interval = rate.interval();
+    let mut pacer = Pacer::new(interval);
+    let start = now_100ns();
+    let mut index = 0;
+    while frames.is_none_or(|frames| index < frames) {
+        let timestamp = start.saturating_add(index.saturating_mul(interval));
+        index += 1;
+        match pacer.pace(timestamp) {
+            Pace::Send => {}
+            Pace::Hold(wait) => std::thread::sleep(wait),
+            Pace::Drop => continue,
+        }
+        let data = generator.render(index - 1, timestamp - start);
+        match camera.send(Frame::with_timestamp(data, timestamp)) {
+            Ok(()) | Err(Error::SendresWarnFrameskip) | Err(Error::FrameDropped) => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn test_pattern_generator() {
+    let bars = PatternGenerator::new(Pattern::ColorBars, 84, 12, PixelFormat::Rgb);
+    let frame = bars.render(0, 0);
+    assert_eq!(frame.len(), 84 * 12 * 3);
+    // first top bar is 75% white, last is blue
+    assert_eq!(&frame[..3], &[191, 191, 191]);
+    assert_eq!(&frame[83 * 3..84 * 3], &[0, 0, 191]);
+
+    let counter = PatternGenerator::new(Pattern::Counter, 64, 48, PixelFormat::Nv12);
+    assert_ne!(counter.render(1, 0), counter.render(2, 0));
+    assert_eq!(counter.render(1, 0).len(), 64 * 48 * 3 / 2);
+
+    // 16x16 squares on a 128-high frame, white in the top left corner
+    let checker = PatternGenerator::new(Pattern::Checkerboard, 128, 128, PixelFormat::Rgb);
+    let frame = checker.render(0, 0);
+    let at = |x: usize, y: usize| &frame[(y * 128 + x) * 3..][..3];
+    assert_eq!((at(0, 0), at(15, 15)), (&[255; 3][..], &[255; 3][..]));
+    assert_eq!((at(16, 0), at(0, 16)), (&[0; 3][..], &[0; 3][..]));
+    assert_eq!(at(16, 16), &[255; 3]);
+    assert_eq!(checker.render(5, 0), frame);
+
+    let gradient = PatternGenerator::new(Pattern::Gradient, 64, 8, PixelFormat::Rgb);
+    assert_ne!(gradient.render(0, 0), gradient.render(1, 0));
+    assert_eq!(gradient.render(0, 0), gradient.render(64, 0));
+
+    let solid = PatternGenerator::new(Pattern::Solid([1, 2, 3]), 4, 2, PixelFormat::Bgra);
+    assert_eq!(solid.render(0, 0), [3, 2, 1, 255].repeat(8));
+    assert_eq!(
+        "solid:010203".parse::<Pattern>().unwrap(),
+        Pattern::Solid([1, 2, 3])
+    );
+}
+
+#[test]
+fn test_stream_frame_count() {
+    use crate::mock::{MockAction, MockBackend};
+    use crate::Backend;
+
+    let mock = MockBackend::new(4, 2, PixelFormat::Rgba).on_send(1, MockAction::Skip);
+    let mut camera = Camera::from_backend(Backend::Mock(mock.clone()));
+    let rate = FrameRate::new(240, 1).unwrap();
+    stream(&mut camera, Pattern::ColorBars, rate, Some(5)).unwrap();
+    let frames = mock.frames();
+    assert_eq!(frames.len(), 5);
+    assert_eq!(
+        frames[4].timestamp - frames[0].timestamp,
+        4 * rate.interval()
+    );
+}
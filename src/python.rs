@@ -0,0 +1,194 @@
+//! Python bindings, built with `maturin build --features python`.
+//!
+//! ```python
+//! import numpy as np
+//! import virtualcam_rs
+//!
+//! with virtualcam_rs.Camera(width=1280, height=720, fps=30) as cam:
+//!     frame = np.zeros((cam.height, cam.width, 3), np.uint8)
+//!     while True:
+//!         cam.send(frame)
+//!         cam.sleep_until_next_frame()
+//! ```
+
+// pyo3 0.22's generated method wrappers trip this lint
+#![allow(clippy::useless_conversion)]
+
+use numpy::PyReadonlyArray3;
+use pyo3::exceptions::{PyRuntimeError, PyValueError};
+use pyo3::prelude::*;
+
+use crate::format::PixelFormat;
+use crate::into_frame::IntoFrame;
+use crate::mock::MockBackend;
+use crate::pacer::{FrameClock, FrameRate};
+use crate::Error;
+
+impl From<Error> for PyErr {
+    fn from(e: Error) -> Self {
+        match e {
+            Error::InvalidArgument | Error::InvalidFrameSize | Error::UnsupportedFormat => {
+                PyValueError::new_err(format!("{:?}", e))
+            }
+            e => PyRuntimeError::new_err(format!("{:?}", e)),
+        }
+    }
+}
+
+#[pyclass(name = "Camera")]
+struct PyCamera {
+    camera: Option<crate::Camera>,
+    clock: FrameClock,
+    fps: f64,
+    device: String,
+    backend: String,
+}
+
+impl PyCamera {
+    fn camera(&mut self) -> PyResult<&mut crate::Camera> {
+        self.camera
+            .as_mut()
+            .ok_or_else(|| PyRuntimeError::new_err("camera is closed"))
+    }
+}
+
+#[pymethods]
+impl PyCamera {
+    #[new]
+    #[pyo3(signature = (width, height, fps, device=None, backend=None))]
+    fn new(
+        width: u32,
+        height: u32,
+        fps: f64,
+        device: Option<String>,
+        backend: Option<String>,
+    ) -> PyResult<Self> {
+        let rate = FrameRate::from_fps(fps)
+            .map_err(|_| PyValueError::new_err(format!("unsupported fps {}", fps)))?;
+        let device = device.unwrap_or_else(|| "Unity Video Capture".to_owned());
+        let backend = backend.unwrap_or_else(|| "unity".to_owned());
+        let camera = match backend.as_str() {
+            "unity" => crate::Camera::new(width as i32, height as i32, &device)?,
+            "obs" => crate::Camera::obs(width, height, rate)?,
+            "mock" => crate::Camera::from_backend(crate::Backend::Mock(MockBackend::new(
+                width,
+                height,
+                PixelFormat::Rgba,
+            ))),
+            _ => {
+                return Err(PyValueError::new_err(format!(
+                    "unsupported backend {}",
+                    backend
+                )))
+            }
+        };
+        Ok(Self {
+            camera: Some(camera),
+            clock: FrameClock::new(rate),
+            fps,
+            device,
+            backend,
+        })
+    }
+
+    #[getter]
+    fn width(&mut self) -> PyResult<u32> {
+        Ok(self.camera()?.width())
+    }
+
+    #[getter]
+    fn height(&mut self) -> PyResult<u32> {
+        Ok(self.camera()?.height())
+    }
+
+    #[getter]
+    fn fps(&self) -> f64 {
+        self.fps
+    }
+
+    #[getter]
+    fn device(&self) -> &str {
+        &self.device
+    }
+
+    #[getter]
+    fn backend(&self) -> &str {
+        &self.backend
+    }
+
+    /// Sends an HxWx3 (RGB) or HxWx4 (RGBA) uint8 array. Arrays of another
+    /// size are fitted to the camera with black bars.
+    fn send(&mut self, py: Python<'_>, frame: PyReadonlyArray3<u8>) -> PyResult<()> {
+        let camera = self.camera()?;
+        let (width, height, format) = (camera.width(), camera.height(), camera.format());
+        let frame = frame.as_array().into_frame(width, height, format)?;
+        match py.allow_threads(|| camera.send(frame)) {
+            Ok(()) | Err(Error::SendresWarnFrameskip) | Err(Error::FrameDropped) => Ok(()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Sleeps until the next frame is due at the camera's frame rate.
+    fn sleep_until_next_frame(&mut self, py: Python<'_>) {
+        let clock = &mut self.clock;
+        py.allow_threads(|| clock.sleep_until_next_frame());
+    }
+
+    fn close(&mut self) {
+        self.camera = None;
+    }
+
+    fn __enter__(slf: Py<Self>) -> Py<Self> {
+        slf
+    }
+
+    fn __exit__(
+        &mut self,
+        _exc_type: PyObject,
+        _exc_value: PyObject,
+        _traceback: PyObject,
+    ) -> bool {
+        self.close();
+        false
+    }
+}
+
+#[pymodule]
+#[pyo3(name = "virtualcam_rs")]
+fn virtualcam_rs_module(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyCamera>()?;
+    Ok(())
+}
+
+#[test]
+fn test_python_camera() {
+    use pyo3::types::IntoPyDict;
+
+    pyo3::prepare_freethreaded_python();
+    Python::with_gil(|py| {
+        let module = PyModule::new_bound(py, "virtualcam_rs").unwrap();
+        virtualcam_rs_module(&module).unwrap();
+        let locals = [("virtualcam_rs", module)].into_py_dict_bound(py);
+        py.run_bound(
+            r#"
+with virtualcam_rs.Camera(4, 2, 30, backend="mock") as cam:
+    assert (cam.width, cam.height, cam.fps, cam.backend) == (4, 2, 30, "mock")
+    cam.sleep_until_next_frame()
+try:
+    cam.width
+    raise AssertionError("camera still open")
+except RuntimeError:
+    pass
+for fps in (0, -1, 1e-300, float("nan")):
+    try:
+        virtualcam_rs.Camera(4, 2, fps, backend="mock")
+        raise AssertionError(fps)
+    except ValueError:
+        pass
+"#,
+            None,
+            Some(&locals),
+        )
+        .unwrap();
+    });
+}
@@ -0,0 +1,172 @@
+use std::str::FromStr;
+
+use crate::format::{convert, PixelFormat};
+use crate::Error;
+
+/// How a source is mapped onto a differently shaped destination.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FitMode {
+    /// Fill the destination, ignoring aspect ratio.
+    Stretch,
+    /// Keep aspect ratio and show the whole source, with black bars.
+    #[default]
+    Fit,
+    /// Keep aspect ratio and cover the destination, cropping the source.
+    Fill,
+}
+
+impl FromStr for FitMode {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Error> {
+        match s.to_ascii_lowercase().as_str() {
+            "stretch" => Ok(FitMode::Stretch),
+            "fit" | "letterbox" => Ok(FitMode::Fit),
+            "fill" | "crop" => Ok(FitMode::Fill),
+            _ => Err(Error::InvalidArgument),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Rect {
+    x: f64,
+    y: f64,
+    width: f64,
+    height: f64,
+}
+
+/// Bilinearly scales a tightly packed RGBA frame. Areas the source does not
+/// cover are opaque black.
+pub fn scale_rgba(
+    src: &[u8],
+    src_width: u32,
+    src_height: u32,
+    dst_width: u32,
+    dst_height: u32,
+    fit: FitMode,
+) -> Vec<u8> {
+    let (sw, sh) = (src_width as f64, src_height as f64);
+    let (dw, dh) = (dst_width as f64, dst_height as f64);
+    let full_src = Rect {
+        x: 0.0,
+        y: 0.0,
+        width: sw,
+        height: sh,
+    };
+    let full_dst = Rect {
+        x: 0.0,
+        y: 0.0,
+        width: dw,
+        height: dh,
+    };
+    let (src_rect, dst_rect) = match fit {
+        FitMode::Stretch => (full_src, full_dst),
+        FitMode::Fit => {
+            let scale = (dw / sw).min(dh / sh);
+            let (width, height) = ((sw * scale).round(), (sh * scale).round());
+            let dst_rect = Rect {
+                x: ((dw - width) / 2.0).floor(),
+                y: ((dh - height) / 2.0).floor(),
+                width,
+                height,
+            };
+            (full_src, dst_rect)
+        }
+        FitMode::Fill => {
+            let scale = (dw / sw).max(dh / sh);
+            let (width, height) = (dw / scale, dh / scale);
+            let src_rect = Rect {
+                x: (sw - width) / 2.0,
+                y: (sh - height) / 2.0,
+                width,
+                height,
+            };
+            (src_rect, full_dst)
+        }
+    };
+
+    let (w, h) = (dst_width as usize, dst_height as usize);
+    let mut dst = vec![0; w * h * 4];
+    for px in dst.chunks_exact_mut(4) {
+        px[3] = 255;
+    }
+    if src_width == 0 || src_height == 0 {
+        return dst;
+    }
+    let x_start = dst_rect.x as usize;
+    let y_start = dst_rect.y as usize;
+    let x_end = ((dst_rect.x + dst_rect.width) as usize).min(w);
+    let y_end = ((dst_rect.y + dst_rect.height) as usize).min(h);
+    let sample_at = |pos: usize, start: f64, len: f64, src_start: f64, src_len: f64, max: u32| {
+        let f = src_start + (pos as f64 - start + 0.5) * src_len / len - 0.5;
+        let f = f.clamp(0.0, (max - 1) as f64);
+        let lo = f.floor() as usize;
+        let hi = (lo + 1).min(max as usize - 1);
+        (lo, hi, f - lo as f64)
+    };
+    for y in y_start..y_end {
+        let (y0, y1, ty) = sample_at(
+            y,
+            dst_rect.y,
+            dst_rect.height,
+            src_rect.y,
+            src_rect.height,
+            src_height,
+        );
+        for x in x_start..x_end {
+            let (x0, x1, tx) = sample_at(
+                x,
+                dst_rect.x,
+                dst_rect.width,
+                src_rect.x,
+                src_rect.width,
+                src_width,
+            );
+            let at =
+                |x: usize, y: usize, c: usize| src[(y * src_width as usize + x) * 4 + c] as f64;
+            for c in 0..4 {
+                let top = at(x0, y0, c) * (1.0 - tx) + at(x1, y0, c) * tx;
+                let bottom = at(x0, y1, c) * (1.0 - tx) + at(x1, y1, c) * tx;
+                dst[(y * w + x) * 4 + c] = (top * (1.0 - ty) + bottom * ty).round() as u8;
+            }
+        }
+    }
+    dst
+}
+
+/// Scales a frame in any format, converting through RGBA when the size changes.
+#[allow(clippy::too_many_arguments)]
+pub fn scale(
+    src: &[u8],
+    from: PixelFormat,
+    src_width: u32,
+    src_height: u32,
+    to: PixelFormat,
+    dst_width: u32,
+    dst_height: u32,
+    fit: FitMode,
+) -> Result<Vec<u8>, Error> {
+    if (src_width, src_height) == (dst_width, dst_height) {
+        return convert(src, from, to, src_width, src_height);
+    }
+    let rgba = convert(src, from, PixelFormat::Rgba, src_width, src_height)?;
+    let scaled = scale_rgba(&rgba, src_width, src_height, dst_width, dst_height, fit);
+    convert(&scaled, PixelFormat::Rgba, to, dst_width, dst_height)
+}
+
+#[test]
+fn test_scale_fit_modes() {
+    // 2x1 white source into a 4x4 frame
+    let src = vec![255; 2 * 4];
+    let fit = scale_rgba(&src, 2, 1, 4, 4, FitMode::Fit);
+    // letterboxed: rows 0 and 3 black, rows 1-2 white
+    assert_eq!(&fit[..4], &[0, 0, 0, 255]);
+    assert_eq!(&fit[4 * 4..4 * 4 + 4], &[255, 255, 255, 255]);
+    assert_eq!(&fit[3 * 16..3 * 16 + 4], &[0, 0, 0, 255]);
+
+    let fill = scale_rgba(&src, 2, 1, 4, 4, FitMode::Fill);
+    assert!(fill.iter().all(|&b| b == 255));
+    let stretch = scale_rgba(&src, 2, 1, 4, 4, FitMode::Stretch);
+    assert!(stretch.iter().all(|&b| b == 255));
+}
@@ -0,0 +1,280 @@
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read};
+use std::path::Path;
+
+use crate::format::PixelFormat;
+use crate::pacer::{now_100ns, FrameRate, Pace, Pacer};
+use crate::scale::{scale, FitMode};
+use crate::{Camera, Error, Frame};
+
+/// Frame rate used when neither the stream nor the caller specifies one.
+const DEFAULT_RATE: FrameRate = FrameRate::from_integer(30);
+
+/// A sequence of decoded frames of fixed size and format.
+pub trait VideoSource {
+    fn width(&self) -> u32;
+    fn height(&self) -> u32;
+    fn format(&self) -> PixelFormat;
+    /// Frame rate, if the stream declares one.
+    fn frame_rate(&self) -> Option<FrameRate>;
+    /// The next frame, or `None` at the end of the stream.
+    fn next_frame(&mut self) -> Result<Option<Vec<u8>>, Error>;
+}
+
+/// Opens a file for reading, or stdin when `path` is `-`.
+pub fn open_input<P: AsRef<Path>>(path: P) -> Result<Box<dyn BufRead + Send>, Error> {
+    let path = path.as_ref();
+    if path == Path::new("-") {
+        return Ok(Box::new(BufReader::new(io::stdin())));
+    }
+    let file = File::open(path).map_err(Error::Io)?;
+    Ok(Box::new(BufReader::new(file)))
+}
+
+/// Fills `buf` completely. Returns `false` on a clean end of stream before
+/// the first byte, and an error if the stream ends part way through.
+fn read_frame<R: Read>(reader: &mut R, buf: &mut [u8]) -> Result<bool, Error> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..]) {
+            Ok(0) if filled == 0 => return Ok(false),
+            Ok(0) => return Err(Error::Io(io::ErrorKind::UnexpectedEof.into())),
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(Error::Io(e)),
+        }
+    }
+    Ok(true)
+}
+
+/// Headerless frames of a size and format given by the caller.
+pub struct RawSource<R> {
+    reader: R,
+    width: u32,
+    height: u32,
+    format: PixelFormat,
+    frame_rate: Option<FrameRate>,
+}
+
+impl<R: Read> RawSource<R> {
+    pub fn new(reader: R, width: u32, height: u32, format: PixelFormat) -> Self {
+        Self {
+            reader,
+            width,
+            height,
+            format,
+            frame_rate: None,
+        }
+    }
+
+    pub fn frame_rate(mut self, rate: FrameRate) -> Self {
+        self.frame_rate = Some(rate);
+        self
+    }
+}
+
+impl<R: Read> VideoSource for RawSource<R> {
+    fn width(&self) -> u32 {
+        self.width
+    }
+
+    fn height(&self) -> u32 {
+        self.height
+    }
+
+    fn format(&self) -> PixelFormat {
+        self.format
+    }
+
+    fn frame_rate(&self) -> Option<FrameRate> {
+        self.frame_rate
+    }
+
+    fn next_frame(&mut self) -> Result<Option<Vec<u8>>, Error> {
+        let mut frame = vec![0; self.format.frame_size(self.width, self.height)];
+        match read_frame(&mut self.reader, &mut frame)? {
+            true => Ok(Some(frame)),
+            false => Ok(None),
+        }
+    }
+}
+
+/// A YUV4MPEG2 stream, as written by `ffmpeg -f yuv4mpegpipe`.
+///
+/// Only 8-bit 4:2:0 colour spaces are accepted; frames come out as `I420`.
+pub struct Y4mSource<R> {
+    reader: R,
+    width: u32,
+    height: u32,
+    frame_rate: Option<FrameRate>,
+}
+
+impl<R: BufRead> Y4mSource<R> {
+    pub fn new(mut reader: R) -> Result<Self, Error> {
+        let header = read_line(&mut reader)?
+            .ok_or_else(|| Error::InvalidStream("empty y4m stream".to_owned()))?;
+        let mut params = header.split(' ');
+        if params.next() != Some("YUV4MPEG2") {
+            return Err(Error::InvalidStream(
+                "missing YUV4MPEG2 signature".to_owned(),
+            ));
+        }
+        let (mut width, mut height, mut frame_rate) = (None, None, None);
+        for param in params.filter(|p| !p.is_empty()) {
+            let invalid = || Error::InvalidStream(format!("bad y4m parameter {}", param));
+            let (tag, value) = param.split_at_checked(1).ok_or_else(invalid)?;
+            match tag {
+                "W" => width = Some(value.parse::<u32>().map_err(|_| invalid())?),
+                "H" => height = Some(value.parse::<u32>().map_err(|_| invalid())?),
+                "F" => {
+                    let (num, den) = value.split_once(':').ok_or_else(invalid)?;
+                    let num = num.parse::<u32>().map_err(|_| invalid())?;
+                    let den = den.parse::<u32>().map_err(|_| invalid())?;
+                    // F0:0 means unknown
+                    frame_rate = FrameRate::new(num, den).ok();
+                }
+                "C" => match value {
+                    "420" | "420jpeg" | "420paldv" | "420mpeg2" => {}
+                    _ => return Err(Error::UnsupportedFormat),
+                },
+                _ => {}
+            }
+        }
+        match (width, height) {
+            (Some(width), Some(height)) => Ok(Self {
+                reader,
+                width,
+                height,
+                frame_rate,
+            }),
+            _ => Err(Error::InvalidStream("y4m header lacks W or H".to_owned())),
+        }
+    }
+}
+
+impl<R: BufRead> VideoSource for Y4mSource<R> {
+    fn width(&self) -> u32 {
+        self.width
+    }
+
+    fn height(&self) -> u32 {
+        self.height
+    }
+
+    fn format(&self) -> PixelFormat {
+        PixelFormat::I420
+    }
+
+    fn frame_rate(&self) -> Option<FrameRate> {
+        self.frame_rate
+    }
+
+    fn next_frame(&mut self) -> Result<Option<Vec<u8>>, Error> {
+        let Some(header) = read_line(&mut self.reader)? else {
+            return Ok(None);
+        };
+        if !header.starts_with("FRAME") {
+            return Err(Error::InvalidStream("missing FRAME marker".to_owned()));
+        }
+        let mut frame = vec![0; PixelFormat::I420.frame_size(self.width, self.height)];
+        match read_frame(&mut self.reader, &mut frame)? {
+            true => Ok(Some(frame)),
+            false => Err(Error::Io(io::ErrorKind::UnexpectedEof.into())),
+        }
+    }
+}
+
+/// Reads one `\n`-terminated header line, `None` at the end of the stream.
+fn read_line<R: BufRead>(reader: &mut R) -> Result<Option<String>, Error> {
+    let mut line = Vec::new();
+    if reader.read_until(b'\n', &mut line).map_err(Error::Io)? == 0 {
+        return Ok(None);
+    }
+    if line.last() == Some(&b'\n') {
+        line.pop();
+    }
+    String::from_utf8(line)
+        .map(Some)
+        .map_err(|_| Error::InvalidStream("header is not text".to_owned()))
+}
+
+/// Opens `path` (or stdin for `-`) as a Y4M stream.
+pub fn open_y4m<P: AsRef<Path>>(path: P) -> Result<Y4mSource<Box<dyn BufRead + Send>>, Error> {
+    Y4mSource::new(open_input(path)?)
+}
+
+/// Plays `source` on `camera` until the end of the stream, scaling and
+/// converting every frame to the camera's size and format. Frames are timed
+/// from the stream's frame rate unless `rate` overrides it. Returns the
+/// number of frames read.
+pub fn play(
+    camera: &mut Camera,
+    source: &mut dyn VideoSource,
+    fit: FitMode,
+    rate: Option<FrameRate>,
+) -> Result<u64, Error> {
+    let rate = rate.or(source.frame_rate()).unwrap_or(DEFAULT_RATE);
+    let interval = rate.interval();
+    let mut pacer = Pacer::new(interval);
+    let start = now_100ns();
+    let mut index: u64 = 0;
+    while let Some(data) = source.next_frame()? {
+        let timestamp = start.saturating_add(index.saturating_mul(interval));
+        index += 1;
+        match pacer.pace(timestamp) {
+            Pace::Send => {}
+            Pace::Hold(wait) => std::thread::sleep(wait),
+            Pace::Drop => continue,
+        }
+        let data = scale(
+            &data,
+            source.format(),
+            source.width(),
+            source.height(),
+            camera.format(),
+            camera.width(),
+            camera.height(),
+            fit,
+        )?;
+        match camera.send(Frame::with_timestamp(data, timestamp)) {
+            Ok(()) | Err(Error::SendresWarnFrameskip) | Err(Error::FrameDropped) => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(index)
+}
+
+#[test]
+fn test_y4m_and_raw_sources() {
+    let mut y4m = b"YUV4MPEG2 W2 H2 F30000:1001 Ip A1:1 C420jpeg\n".to_vec();
+    for i in 0..2u8 {
+        y4m.extend_from_slice(b"FRAME\n");
+        y4m.extend_from_slice(&[i; 6]);
+    }
+    let mut source = Y4mSource::new(&y4m[..]).unwrap();
+    assert_eq!((source.width(), source.height()), (2, 2));
+    assert_eq!(
+        source.frame_rate(),
+        Some(FrameRate::new(30000, 1001).unwrap())
+    );
+    assert_eq!(source.next_frame().unwrap(), Some(vec![0; 6]));
+    assert_eq!(source.next_frame().unwrap(), Some(vec![1; 6]));
+    assert_eq!(source.next_frame().unwrap(), None);
+
+    assert!(Y4mSource::new(&b"YUV4MPEG2 W2 H2 C444\n"[..]).is_err());
+    assert!(matches!(
+        Y4mSource::new(&b"YUV4MPEG2 W2 H2 C420p10\n"[..]),
+        Err(Error::UnsupportedFormat)
+    ));
+    assert!(Y4mSource::new(&b"YUV4MPEG2 W2 H2 C420paldv\n"[..]).is_ok());
+    assert!(matches!(
+        Y4mSource::new("YUV4MPEG2 W2 H2 \u{e9}x\n".as_bytes()),
+        Err(Error::InvalidStream(_))
+    ));
+
+    let raw = [7u8; 2 * 2 * 3 + 5];
+    let mut source = RawSource::new(&raw[..], 2, 2, PixelFormat::Rgb);
+    assert_eq!(source.next_frame().unwrap(), Some(vec![7; 12]));
+    // a trailing partial frame is an error, not a silent end
+    assert!(source.next_frame().is_err());
+}
@@ -0,0 +1,273 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use log::debug;
+
+use crate::Error;
+
+/// Upper bounds of the send latency buckets; a last, unbounded bucket holds
+/// slower sends.
+pub const LATENCY_BUCKETS: [Duration; 10] = [
+    Duration::from_micros(50),
+    Duration::from_micros(100),
+    Duration::from_micros(250),
+    Duration::from_micros(500),
+    Duration::from_millis(1),
+    Duration::from_micros(2500),
+    Duration::from_millis(5),
+    Duration::from_millis(10),
+    Duration::from_millis(25),
+    Duration::from_millis(50),
+];
+/// How far back [`CameraStats::fps`] looks.
+pub const FPS_WINDOW: Duration = Duration::from_secs(1);
+
+/// How long backend sends took, excluding time spent held by a pacer.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LatencyHistogram {
+    /// `counts[i]` is the number of sends that took at most
+    /// `LATENCY_BUCKETS[i]` and longer than the bucket before.
+    pub counts: [u64; LATENCY_BUCKETS.len() + 1],
+    pub total: Duration,
+    pub max: Duration,
+}
+
+impl LatencyHistogram {
+    pub fn record(&mut self, latency: Duration) {
+        let bucket = LATENCY_BUCKETS
+            .iter()
+            .position(|&bound| latency <= bound)
+            .unwrap_or(LATENCY_BUCKETS.len());
+        self.counts[bucket] += 1;
+        self.total += latency;
+        self.max = self.max.max(latency);
+    }
+
+    pub fn count(&self) -> u64 {
+        self.counts.iter().sum()
+    }
+
+    pub fn mean(&self) -> Option<Duration> {
+        match self.count() {
+            0 => None,
+            count => Some(Duration::from_nanos(
+                (self.total.as_nanos() / count as u128) as u64,
+            )),
+        }
+    }
+
+    /// Upper bound of the bucket holding the `q` quantile (0.0–1.0), or the
+    /// maximum if that is the unbounded bucket.
+    pub fn quantile(&self, q: f64) -> Option<Duration> {
+        let count = self.count();
+        if count == 0 {
+            return None;
+        }
+        let rank = ((q.clamp(0.0, 1.0) * count as f64).ceil() as u64).max(1);
+        let mut seen = 0;
+        for (i, &n) in self.counts.iter().enumerate() {
+            seen += n;
+            if seen >= rank {
+                return Some(LATENCY_BUCKETS.get(i).copied().unwrap_or(self.max));
+            }
+        }
+        Some(self.max)
+    }
+}
+
+/// What a [`Camera`](crate::Camera) has done since it was created or its
+/// stats were last reset.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct CameraStats {
+    /// Frames the backend accepted.
+    pub sent: u64,
+    /// Frames the consumer skipped (`Error::SendresWarnFrameskip`).
+    pub skipped: u64,
+    /// Frames sent with a timestamp no later than the frame before.
+    pub duplicated: u64,
+    /// Frames the pacer dropped for arriving late or too soon after the
+    /// one before (`Error::FrameDropped`).
+    pub dropped: u64,
+    /// Frames rejected as too large or of the wrong size
+    /// (`Error::SendresToolarge`, `Error::InvalidFrameSize`).
+    pub too_large: u64,
+    /// Sends that failed with any other error.
+    pub errors: u64,
+    /// Frame bytes handed to the backend by sent and skipped frames.
+    pub bytes_copied: u64,
+    /// Frames sent per second over the last [`FPS_WINDOW`].
+    pub fps: f64,
+    pub latency: LatencyHistogram,
+}
+
+/// Keeps a camera's [`CameraStats`] up to date and, with the `metrics`
+/// feature, mirrors them to the `metrics` facade labelled with the backend.
+#[derive(Debug)]
+pub(crate) struct StatsRecorder {
+    backend: &'static str,
+    stats: CameraStats,
+    recent: VecDeque<Instant>,
+    last_timestamp: Option<u64>,
+    /// Consecutive skipped frames, logged as one burst.
+    skip_run: u64,
+}
+
+impl StatsRecorder {
+    pub fn new(backend: &'static str) -> Self {
+        Self {
+            backend,
+            stats: CameraStats::default(),
+            recent: VecDeque::new(),
+            last_timestamp: None,
+            skip_run: 0,
+        }
+    }
+
+    #[cfg_attr(not(feature = "tracing"), allow(dead_code))]
+    pub fn backend(&self) -> &'static str {
+        self.backend
+    }
+
+    pub fn record_dropped(&mut self) {
+        self.stats.dropped += 1;
+        #[cfg(feature = "metrics")]
+        metrics::counter!("vcam_frames_dropped_total", "backend" => self.backend).increment(1);
+    }
+
+    /// Records one backend send of `len` bytes stamped `timestamp`.
+    pub fn record(
+        &mut self,
+        result: &Result<(), Error>,
+        len: usize,
+        timestamp: u64,
+        latency: Duration,
+    ) {
+        let stats = &mut self.stats;
+        stats.latency.record(latency);
+        #[cfg_attr(not(feature = "metrics"), allow(unused_variables))]
+        let (counter, copied) = match result {
+            Ok(()) => {
+                stats.sent += 1;
+                let now = Instant::now();
+                self.recent.push_back(now);
+                while self
+                    .recent
+                    .front()
+                    .is_some_and(|&t| now.duration_since(t) > FPS_WINDOW)
+                {
+                    self.recent.pop_front();
+                }
+                ("vcam_frames_sent_total", true)
+            }
+            Err(Error::SendresWarnFrameskip) => {
+                stats.skipped += 1;
+                self.skip_run += 1;
+                if self.skip_run == 1 {
+                    debug!(backend = self.backend; "consumer started skipping frames");
+                }
+                ("vcam_frames_skipped_total", true)
+            }
+            Err(Error::SendresToolarge | Error::InvalidFrameSize) => {
+                stats.too_large += 1;
+                ("vcam_frames_too_large_total", false)
+            }
+            Err(_) => {
+                stats.errors += 1;
+                ("vcam_send_errors_total", false)
+            }
+        };
+        if result.is_ok() && self.skip_run > 0 {
+            debug!(
+                backend = self.backend, frames = self.skip_run;
+                "consumer stopped skipping frames"
+            );
+            self.skip_run = 0;
+        }
+        if copied {
+            stats.bytes_copied += len as u64;
+            if self.last_timestamp.is_some_and(|last| timestamp <= last) {
+                stats.duplicated += 1;
+            }
+            self.last_timestamp = Some(timestamp);
+        }
+        #[cfg(feature = "metrics")]
+        {
+            let backend = self.backend;
+            metrics::counter!(counter, "backend" => backend).increment(1);
+            metrics::histogram!("vcam_send_latency_seconds", "backend" => backend)
+                .record(latency.as_secs_f64());
+            if copied {
+                metrics::counter!("vcam_bytes_copied_total", "backend" => backend)
+                    .increment(len as u64);
+            }
+        }
+    }
+
+    pub fn snapshot(&self) -> CameraStats {
+        let fps = match (self.recent.front(), self.recent.back()) {
+            (Some(first), Some(last)) if self.recent.len() > 1 && last.elapsed() <= FPS_WINDOW => {
+                (self.recent.len() - 1) as f64 / last.duration_since(*first).as_secs_f64()
+            }
+            _ => 0.0,
+        };
+        CameraStats { fps, ..self.stats }
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self::new(self.backend);
+    }
+}
+
+#[test]
+fn test_camera_stats() {
+    use crate::format::PixelFormat;
+    use crate::mock::{MockAction, MockBackend};
+    use crate::pacer::Pacer;
+    use crate::{Backend, Camera, Frame};
+
+    let mock = MockBackend::new(2, 1, PixelFormat::Rgba)
+        .on_send(1, MockAction::Skip)
+        .on_send(3, MockAction::Fail(Error::MutexTimeout));
+    let mut camera = Camera::from_backend(Backend::Mock(mock));
+    for ts in [10, 20, 20, 30, 40] {
+        camera.send(Frame::with_timestamp(vec![0; 8], ts)).ok();
+    }
+    assert!(camera.send(vec![0; 3]).is_err());
+
+    let stats = camera.stats();
+    assert_eq!((stats.sent, stats.skipped, stats.errors), (3, 1, 1));
+    assert_eq!((stats.too_large, stats.duplicated), (1, 1));
+    assert_eq!(stats.bytes_copied, 32);
+    assert_eq!(stats.latency.count(), 6);
+    assert!(stats.fps > 0.0);
+    assert!(stats.latency.mean().is_some());
+
+    camera.set_pacer(Some(Pacer::from_fps(30.0).unwrap()));
+    camera.send(Frame::with_timestamp(vec![0; 8], 1_000)).ok();
+    assert!(matches!(
+        camera.send(Frame::with_timestamp(vec![0; 8], 1_000)),
+        Err(Error::FrameDropped)
+    ));
+    assert_eq!(camera.stats().dropped, 1);
+    camera.reset_stats();
+    assert_eq!(camera.stats(), CameraStats::default());
+
+    let mut histogram = LatencyHistogram::default();
+    for us in [10, 60, 60, 200_000] {
+        histogram.record(Duration::from_micros(us));
+    }
+    assert_eq!(histogram.counts[0..2], [1, 2]);
+    assert_eq!(histogram.quantile(0.5), Some(LATENCY_BUCKETS[1]));
+    assert_eq!(histogram.quantile(1.0), Some(Duration::from_millis(200)));
+    assert_eq!(
+        histogram.mean(),
+        Some(Duration::from_micros(50_032) + Duration::from_nanos(500))
+    );
+    // more sends than fit in a u32
+    let histogram = LatencyHistogram {
+        counts: [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1 << 33],
+        total: Duration::from_secs(1 << 33),
+        max: Duration::from_secs(1),
+    };
+    assert_eq!(histogram.mean(), Some(Duration::from_secs(1)));
+}
@@ -0,0 +1,148 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::format::{convert, PixelFormat};
+use crate::pacer::{now_100ns, FrameRate, Pace, Pacer};
+use crate::scale::{scale_rgba, FitMode};
+use crate::{Camera, Error, Frame};
+
+/// How often [`stream`] looks at the file's modification time.
+const RELOAD_CHECK_INTERVAL: Duration = Duration::from_millis(500);
+
+/// A PNG, JPEG, BMP or PPM file rendered at the camera's size and format.
+pub struct StillImage {
+    path: PathBuf,
+    width: u32,
+    height: u32,
+    format: PixelFormat,
+    fit: FitMode,
+    modified: Option<SystemTime>,
+    frame: Vec<u8>,
+}
+
+impl StillImage {
+    pub fn open<P: AsRef<Path>>(
+        path: P,
+        width: u32,
+        height: u32,
+        format: PixelFormat,
+        fit: FitMode,
+    ) -> Result<Self, Error> {
+        let mut still = Self {
+            path: path.as_ref().to_owned(),
+            width,
+            height,
+            format,
+            fit,
+            modified: None,
+            frame: Vec::new(),
+        };
+        still.load()?;
+        Ok(still)
+    }
+
+    pub fn for_camera<P: AsRef<Path>>(
+        path: P,
+        camera: &Camera,
+        fit: FitMode,
+    ) -> Result<Self, Error> {
+        Self::open(path, camera.width(), camera.height(), camera.format(), fit)
+    }
+
+    /// The converted frame, ready to send.
+    pub fn frame(&self) -> &[u8] {
+        &self.frame
+    }
+
+    /// Reloads the file if its modification time changed. Returns whether a
+    /// new image was loaded; on error the previous image is kept.
+    pub fn reload_if_changed(&mut self) -> Result<bool, Error> {
+        let modified = fs::metadata(&self.path)
+            .and_then(|meta| meta.modified())
+            .map_err(Error::Io)?;
+        if Some(modified) == self.modified {
+            return Ok(false);
+        }
+        self.load()?;
+        Ok(true)
+    }
+
+    fn load(&mut self) -> Result<(), Error> {
+        let modified = fs::metadata(&self.path)
+            .and_then(|meta| meta.modified())
+            .map_err(Error::Io)?;
+        let image = image::open(&self.path)
+            .map_err(|e| Error::ImageDecode(e.to_string()))?
+            .to_rgba8();
+        let rgba = scale_rgba(
+            image.as_raw(),
+            image.width(),
+            image.height(),
+            self.width,
+            self.height,
+            self.fit,
+        );
+        self.frame = convert(
+            &rgba,
+            PixelFormat::Rgba,
+            self.format,
+            self.width,
+            self.height,
+        )?;
+        self.modified = Some(modified);
+        Ok(())
+    }
+}
+
+/// Streams the image at `path` to `camera` at `rate` until an error occurs,
+/// picking up changes to the file as it is rewritten. A half-written file
+/// that fails to decode keeps the previous image on screen.
+pub fn stream<P: AsRef<Path>>(
+    camera: &mut Camera,
+    path: P,
+    fit: FitMode,
+    rate: FrameRate,
+) -> Result<(), Error> {
+    let mut still = StillImage::for_camera(path, camera, fit)?;
+    let interval = rate.interval();
+    let mut pacer = Pacer::new(interval);
+    let start = now_100ns();
+    let mut last_check = Instant::now();
+    for index in 0u64.. {
+        let timestamp = start.saturating_add(index.saturating_mul(interval));
+        match pacer.pace(timestamp) {
+            Pace::Send => {}
+            Pace::Hold(wait) => std::thread::sleep(wait),
+            Pace::Drop => continue,
+        }
+        if last_check.elapsed() >= RELOAD_CHECK_INTERVAL {
+            last_check = Instant::now();
+            still.reload_if_changed().ok();
+        }
+        match camera.send(Frame::with_timestamp(still.frame().to_vec(), timestamp)) {
+            Ok(()) | Err(Error::SendresWarnFrameskip) | Err(Error::FrameDropped) => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn test_still_image_reload() {
+    let path = std::env::temp_dir().join(format!("virtualcam-still-{}.ppm", std::process::id()));
+    fs::write(&path, b"P6 1 1 255\n\xff\x00\x00").unwrap();
+    let mut still = StillImage::open(&path, 2, 2, PixelFormat::Rgb, FitMode::Stretch).unwrap();
+    assert_eq!(still.frame(), &[255, 0, 0].repeat(4)[..]);
+
+    // make sure the modification time moves even on coarse filesystems
+    std::thread::sleep(Duration::from_millis(20));
+    fs::write(&path, b"P6 1 1 255\n\x00\x00\xff").unwrap();
+    let file = fs::File::options().write(true).open(&path).unwrap();
+    file.set_modified(SystemTime::now() + Duration::from_secs(1))
+        .unwrap();
+    assert!(still.reload_if_changed().unwrap());
+    assert_eq!(still.frame(), &[0, 0, 255].repeat(4)[..]);
+    assert!(!still.reload_if_changed().unwrap());
+    fs::remove_file(&path).ok();
+}
@@ -1,14 +1,18 @@
-use std::{ffi::CString, ptr};
+use std::{os::windows::process::CommandExt, path::Path, process::Command, ptr, time::Duration};
 
 use winapi::{
-    shared::{minwindef::DWORD, ntdef::HANDLE},
+    shared::{minwindef::DWORD, ntdef::HANDLE, winerror::WAIT_TIMEOUT},
     um::{
-        handleapi::INVALID_HANDLE_VALUE,
-        memoryapi::MapViewOfFile,
+        handleapi::{CloseHandle, INVALID_HANDLE_VALUE},
+        memoryapi::{
+            CreateFileMappingW, MapViewOfFile, OpenFileMappingW, UnmapViewOfFile,
+            FILE_MAP_ALL_ACCESS,
+        },
         synchapi::{
-            CreateEventA, CreateMutexA, OpenEventA, ReleaseMutex, SetEvent, WaitForSingleObject,
+            CreateEventW, CreateMutexW, OpenEventW, OpenMutexW, ReleaseMutex, SetEvent,
+            WaitForSingleObject,
         },
-        winbase::{CreateFileMappingA, OpenFileMappingA, OpenMutexA, INFINITE, WAIT_OBJECT_0},
+        winbase::{INFINITE, WAIT_ABANDONED, WAIT_OBJECT_0},
         winnt::{EVENT_MODIFY_STATE, PAGE_READWRITE, SYNCHRONIZE},
     },
 };
@@ -16,46 +20,93 @@ use winapi::{
 use winapi::um::memoryapi::FILE_MAP_WRITE;
 use winreg::{enums::HKEY_CLASSES_ROOT, RegKey};
 
+use log::{debug, info, trace, warn};
+
+use crate::metadata::{FrameMetadata, MetadataBlock, MAX_PAYLOAD};
+use crate::wide::WideString;
 use crate::Error;
 
 pub const GUID_OFFSET: u8 = 0x10;
 const MAX_CAPNUM: u32 = 74;
 const MAX_SHARED_IMAGE_SIZE: usize = 3840 * 2160 * 4 * std::mem::size_of::<i16>();
+const DEFAULT_LOCK_TIMEOUT: Duration = Duration::from_secs(1);
+
+fn device_clsid(num: i32) -> String {
+    // 1 is reserved by the library
+    format!(
+        "{{5C2CD55C-92AD-4999-8666-912BD3E700{:02X}}}",
+        GUID_OFFSET + num as u8 + (num != 0) as u8
+    )
+}
+
+/// The friendly name registered for capture device `num`, if any.
+pub fn device_name(num: i32) -> Option<String> {
+    let key_str = format!("CLSID\\{}", device_clsid(num));
+    let reg_key = RegKey::predef(HKEY_CLASSES_ROOT)
+        .open_subkey(key_str)
+        .ok()?;
+    reg_key.get_value("").ok()
+}
+
+/// Registered UnityCapture devices as `(capture number, name)`.
+pub fn list_devices() -> Vec<(u32, String)> {
+    let devices: Vec<_> = (0..MAX_CAPNUM)
+        .filter_map(|i| device_name(i as i32).map(|name| (i, name)))
+        .collect();
+    debug!(count = devices.len(); "listed UnityCapture devices");
+    devices
+}
 
 // 获取UnityCapture的名字
 pub fn get_unity_capture_name(num: i32, cap_name: &str) -> bool {
-    let mut _cap_name_mut: String = cap_name.to_string();
-    // const key_size: usize = 45;
-    // snprintf(key, key_size, "CLSID\\{5C2CD55C-92AD-4999-8666-912BD3E700%02X}", GUID_OFFSET + num + !!num); // 1 is reserved by the library
-
-    let key_str = format!(
-        "CLSID\\{{5C2CD55C-92AD-4999-8666-912BD3E700{:02X}}}",
-        GUID_OFFSET + num as u8 + !!num as u8
-    );
-
-    let reg_key = RegKey::predef(HKEY_CLASSES_ROOT).open_subkey(key_str);
-    match reg_key {
-        Ok(reg_key) => {
-            let value: String = reg_key.get_value("").unwrap();
-            // println!("cap_num:{},  value: {:#?}", num, value);
-            if cap_name == value {
-                return true;
-            }
-            false
+    device_name(num).is_some_and(|name| name == cap_name)
+}
+
+/// Registers the UnityCapture filter DLL with `regsvr32`, which writes the
+/// DirectShow registry entries for `devices` cameras named after `name`.
+/// Needs an elevated process.
+pub fn install(dll: &Path, name: Option<&str>, devices: Option<u32>) -> Result<(), Error> {
+    let mut command = Command::new("regsvr32");
+    command.arg("/s");
+    // regsvr32 hands a single /i: command line to DllInstall.
+    let mut options = Vec::new();
+    if let Some(name) = name {
+        if name.contains('"') {
+            return Err(Error::InvalidArgument);
         }
-        Err(_e) => false,
+        options.push(format!("UnityCaptureName={}", name));
+    }
+    if let Some(devices) = devices {
+        options.push(format!("UnityCaptureDevices={}", devices));
+    }
+    if !options.is_empty() {
+        command.raw_arg(format!("/i:\"{}\"", options.join(" ")));
+    }
+    run_regsvr32(command.arg(dll))
+}
+
+/// Removes the registry entries written by [`install`].
+pub fn uninstall(dll: &Path) -> Result<(), Error> {
+    run_regsvr32(Command::new("regsvr32").args(["/u", "/s"]).arg(dll))
+}
+
+fn run_regsvr32(command: &mut Command) -> Result<(), Error> {
+    let status = command.status().map_err(Error::Io)?;
+    if !status.success() {
+        return Err(Error::Io(std::io::Error::other(format!(
+            "regsvr32 failed with {}",
+            status
+        ))));
     }
+    Ok(())
 }
 
 #[test]
 fn test_get_name() {
-    for i in 0..MAX_CAPNUM {
-        println!(
-            "{}",
-            get_unity_capture_name(i as i32, "Unity Video Capture")
-        );
+    for (num, name) in list_devices() {
+        assert!(get_unity_capture_name(num as i32, &name));
     }
-    // get_name(1, "test".to_string());
+    assert!(!get_unity_capture_name(0, "\0"));
 }
 
 #[cfg(target_os = "windows")]
@@ -64,6 +115,7 @@ pub struct UnityCapture {
     pub height: i32,
     pub device: String,
     pub shared_mem: SharedImageMemory,
+    metadata: Option<MetadataMapping>,
 }
 
 #[cfg(target_os = "windows")]
@@ -71,24 +123,62 @@ impl UnityCapture {
     pub fn new(width: i32, height: i32, device: String) -> Result<Self, Error> {
         for i in 0..MAX_CAPNUM {
             if get_unity_capture_name(i as i32, &device) {
+                debug!(cap_num = i, device = device.as_str(); "found UnityCapture device");
                 return Ok(Self {
                     width,
                     height,
                     device,
                     shared_mem: SharedImageMemory::new(i),
+                    metadata: None,
                 });
             };
         }
+        debug!(device = device.as_str(); "no UnityCapture device with this name");
         Err(Error::UnityCaptureNotFound)
     }
 
+    /// How long to wait for the receiver's mutex, `None` waits forever.
+    pub fn set_lock_timeout(&mut self, timeout: Option<Duration>) {
+        self.shared_mem.set_lock_timeout(timeout);
+    }
+
     pub fn send(&mut self, data: Vec<u8>) -> Result<(), Error> {
-        if !self.shared_mem.send_is_ready() {
-            return Err(Error::UnityCaptureNotInitialized);
-        }
+        self.send_with_metadata(data, None)
+    }
+
+    /// Like [`send`](Self::send), publishing `metadata` on the device's side
+    /// mapping, created on first use. The metadata is written after the frame
+    /// while the receiver's mutex is still held, and only for frames that
+    /// reach the shared buffer.
+    pub fn send_with_metadata(
+        &mut self,
+        data: Vec<u8>,
+        metadata: Option<&FrameMetadata>,
+    ) -> Result<(), Error> {
+        self.shared_mem.open_for_sending()?;
         let timeout = 2147483647 - 200;
 
-        self.shared_mem.send(
+        let metadata = match metadata {
+            Some(metadata) if metadata.payload.len() > MAX_PAYLOAD => {
+                return Err(Error::InvalidArgument)
+            }
+            Some(metadata) => {
+                let mapping = match &mut self.metadata {
+                    Some(mapping) => mapping,
+                    None => {
+                        let mapping = MetadataMapping::open(self.shared_mem.cap_num)?;
+                        debug!(
+                            cap_num = self.shared_mem.cap_num;
+                            "opened UnityCapture metadata mapping"
+                        );
+                        self.metadata.insert(mapping)
+                    }
+                };
+                Some((&mut mapping.block, metadata))
+            }
+            None => None,
+        };
+        self.shared_mem.send_with_metadata(
             self.width,
             self.height,
             self.width,
@@ -98,13 +188,97 @@ impl UnityCapture {
             1,
             timeout,
             data,
+            metadata,
         )
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+/// Name of a shared object of capture device `cap_num`, as the UnityCapture
+/// filter builds it.
+fn object_name(prefix: &str, cap_num: u32) -> Result<WideString, Error> {
+    let suffix = match cap_num {
+        0 => String::new(),
+        num => char::from(b'0' + num as u8).to_string(),
+    };
+    WideString::new(&format!("{}{}", prefix, suffix))
+}
+
+/// The `UnityCapture_Meta` mapping of a device, holding the metadata of the
+/// newest frame. The UnityCapture filter does not know about it; whichever
+/// side comes first creates it.
+#[derive(Debug)]
+struct MetadataMapping {
+    handle: HANDLE,
+    block: MetadataBlock,
+}
+
+impl MetadataMapping {
+    fn open(cap_num: u32) -> Result<Self, Error> {
+        let name = object_name("UnityCapture_Meta", cap_num.min(MAX_CAPNUM))?;
+        let handle = unsafe {
+            CreateFileMappingW(
+                INVALID_HANDLE_VALUE,
+                ptr::null_mut(),
+                PAGE_READWRITE,
+                0,
+                MetadataBlock::SIZE as u32,
+                name.as_ptr(),
+            )
+        };
+        if handle.is_null() {
+            return Err(Error::Io(std::io::Error::last_os_error()));
+        }
+        let view = unsafe { MapViewOfFile(handle, FILE_MAP_ALL_ACCESS, 0, 0, 0) };
+        match unsafe { MetadataBlock::open(view as *mut u8) } {
+            Ok(block) => Ok(Self { handle, block }),
+            Err(_) => {
+                let e = std::io::Error::last_os_error();
+                unsafe { CloseHandle(handle) };
+                Err(Error::Io(e))
+            }
+        }
+    }
+}
+
+// The mapping is process-wide and usable from any thread.
+unsafe impl Send for MetadataMapping {}
+
+impl Drop for MetadataMapping {
+    fn drop(&mut self) {
+        unsafe {
+            UnmapViewOfFile(self.block.as_ptr() as *const _);
+            CloseHandle(self.handle);
+        }
+    }
+}
+
+/// Reads the [`FrameMetadata`] a sender attaches to the frames of capture
+/// device `cap_num`, for receivers that want to match frames to it.
+#[derive(Debug)]
+pub struct MetadataReader {
+    mapping: MetadataMapping,
+    last_seq: Option<u32>,
+}
+
+impl MetadataReader {
+    pub fn open(cap_num: u32) -> Result<Self, Error> {
+        Ok(Self {
+            mapping: MetadataMapping::open(cap_num)?,
+            last_seq: None,
+        })
+    }
+
+    /// The metadata of the newest frame, or `None` if it has not changed
+    /// since the last call.
+    pub fn read(&mut self) -> Result<Option<FrameMetadata>, Error> {
+        self.mapping.block.read(&mut self.last_seq)
+    }
+}
+
+#[derive(Debug)]
 pub struct SharedImageMemory {
     cap_num: u32,
+    lock_timeout: DWORD,
     h_mutex: HANDLE,
     h_want_frame_event: HANDLE,
     h_send_frame_event: HANDLE,
@@ -125,10 +299,14 @@ struct SharedMemHeader {
     data: [u8; 1],
 }
 
+// The handles and the mapped view are process-wide and usable from any thread.
+unsafe impl Send for SharedImageMemory {}
+
 impl SharedImageMemory {
     pub fn new(cap_num: u32) -> Self {
         Self {
-            cap_num: cap_num,
+            cap_num,
+            lock_timeout: DEFAULT_LOCK_TIMEOUT.as_millis() as DWORD,
             h_mutex: 0 as HANDLE,
             h_want_frame_event: 0 as HANDLE,
             h_send_frame_event: 0 as HANDLE,
@@ -137,130 +315,124 @@ impl SharedImageMemory {
         }
     }
 
+    pub fn set_lock_timeout(&mut self, timeout: Option<Duration>) {
+        self.lock_timeout = match timeout {
+            Some(timeout) => timeout.as_millis().min((INFINITE - 1) as u128) as DWORD,
+            None => INFINITE,
+        };
+    }
+
+    /// Waits for the shared mutex for at most the lock timeout.
+    ///
+    /// A mutex abandoned by a crashed receiver, or one held past the timeout,
+    /// closes every shared object so the next call reopens them from scratch.
+    fn lock(&mut self) -> Result<UnlockAtReturn, Error> {
+        let wait = unsafe { WaitForSingleObject(self.h_mutex, self.lock_timeout) };
+        let (error, owned) = match lock_result(wait) {
+            Ok(()) => return Ok(UnlockAtReturn { m: self.h_mutex }),
+            Err(failure) => failure,
+        };
+        match error {
+            Error::MutexAbandoned => warn!(
+                cap_num = self.cap_num;
+                "receiver abandoned the UnityCapture mutex, reconnecting"
+            ),
+            Error::MutexTimeout => warn!(
+                cap_num = self.cap_num, timeout_ms = self.lock_timeout;
+                "timed out waiting for the UnityCapture mutex, reconnecting"
+            ),
+            _ => {
+                let os_error = std::io::Error::last_os_error();
+                warn!(
+                    cap_num = self.cap_num, os_error:%;
+                    "waiting for the UnityCapture mutex failed, reconnecting"
+                );
+            }
+        }
+        if owned {
+            unsafe { ReleaseMutex(self.h_mutex) };
+        }
+        self.close();
+        Err(error)
+    }
+
+    /// Unmaps the shared buffer and closes every handle.
+    pub fn close(&mut self) {
+        unsafe {
+            if !self.m_p_shared_buf.is_null() {
+                UnmapViewOfFile(self.m_p_shared_buf as *const _);
+            }
+            for handle in [
+                self.h_mutex,
+                self.h_want_frame_event,
+                self.h_send_frame_event,
+                self.h_shared_file,
+            ] {
+                if !handle.is_null() {
+                    CloseHandle(handle);
+                }
+            }
+        }
+        self.h_mutex = ptr::null_mut();
+        self.h_want_frame_event = ptr::null_mut();
+        self.h_send_frame_event = ptr::null_mut();
+        self.h_shared_file = ptr::null_mut();
+        self.m_p_shared_buf = ptr::null_mut();
+    }
+
     #[allow(dead_code)]
-    fn open(&mut self, for_receiving: bool) -> bool {
+    fn open(&mut self, for_receiving: bool) -> Result<(), Error> {
         if !self.m_p_shared_buf.is_null() {
-            return true;
+            return Ok(());
         }
         if self.cap_num > MAX_CAPNUM {
             self.cap_num = MAX_CAPNUM;
         }
-        let (cs_name_mutex, cs_name_event_want, cs_name_event_sent, cs_name_shared_data) = if self
-            .cap_num
-            != 0
-        {
-            let cs_cap_num_char = ('0' as u8 + self.cap_num as u8) as char;
-            let cs_name_mutex =
-                CString::new(format!("UnityCapture_Mutx{}", cs_cap_num_char).as_bytes()).unwrap();
-            let cs_name_event_want =
-                CString::new(format!("UnityCapture_Want{}", cs_cap_num_char).as_bytes()).unwrap();
-            let cs_name_event_sent =
-                CString::new(format!("UnityCapture_Sent{}", cs_cap_num_char).as_bytes()).unwrap();
-            let cs_name_shared_data =
-                CString::new(format!("UnityCapture_Data{}", cs_cap_num_char).as_bytes()).unwrap();
-            (
-                cs_name_mutex,
-                cs_name_event_want,
-                cs_name_event_sent,
-                cs_name_shared_data,
-            )
-        } else {
-            let cs_name_mutex = CString::new("UnityCapture_Mutx".as_bytes()).unwrap();
-            let cs_name_event_want = CString::new("UnityCapture_Want".as_bytes()).unwrap();
-            let cs_name_event_sent = CString::new("UnityCapture_Sent".as_bytes()).unwrap();
-            let cs_name_shared_data = CString::new("UnityCapture_Data".as_bytes()).unwrap();
-            (
-                cs_name_mutex,
-                cs_name_event_want,
-                cs_name_event_sent,
-                cs_name_shared_data,
-            )
-        };
+        let name = |prefix: &str| object_name(prefix, self.cap_num);
+        let cs_name_mutex = name("UnityCapture_Mutx")?;
+        let cs_name_event_want = name("UnityCapture_Want")?;
+        let cs_name_event_sent = name("UnityCapture_Sent")?;
+        let cs_name_shared_data = name("UnityCapture_Data")?;
         if self.h_mutex.is_null() {
             match for_receiving {
                 true => {
-                    self.h_mutex = unsafe {
-                        CreateMutexA(std::ptr::null_mut(), 0, cs_name_mutex.as_ptr() as *const i8)
-                    };
-                }
-                false => {
                     self.h_mutex =
-                        unsafe { OpenMutexA(SYNCHRONIZE, 0, cs_name_mutex.as_ptr() as *const i8) };
+                        unsafe { CreateMutexW(std::ptr::null_mut(), 0, cs_name_mutex.as_ptr()) };
                 }
-            }
-            // println!("h_mutex: {:?}", self.h_mutex);
-            if self.h_mutex.is_null() {
-                return false;
-            }
-        }
-        unsafe { WaitForSingleObject(self.h_mutex, INFINITE) };
-        struct UnlockAtReturn {
-            m: HANDLE,
-        }
-
-        impl Drop for UnlockAtReturn {
-            fn drop(&mut self) {
-                unsafe {
-                    ReleaseMutex(self.m);
+                false => {
+                    self.h_mutex = unsafe { OpenMutexW(SYNCHRONIZE, 0, cs_name_mutex.as_ptr()) };
                 }
             }
+            self.check_opened("mutex", self.h_mutex)?;
         }
-
-        let _cs = UnlockAtReturn { m: self.h_mutex };
+        let _cs = self.lock()?;
 
         if self.h_want_frame_event.is_null() {
             match for_receiving {
                 true => {
-                    self.h_want_frame_event = unsafe {
-                        OpenEventA(
-                            EVENT_MODIFY_STATE,
-                            0,
-                            cs_name_event_want.as_ptr() as *const i8,
-                        )
-                    };
+                    self.h_want_frame_event =
+                        unsafe { OpenEventW(EVENT_MODIFY_STATE, 0, cs_name_event_want.as_ptr()) };
                 }
                 false => {
-                    self.h_want_frame_event = unsafe {
-                        CreateEventA(
-                            ptr::null_mut(),
-                            0,
-                            0,
-                            cs_name_event_want.as_ptr() as *const i8,
-                        )
-                    };
+                    self.h_want_frame_event =
+                        unsafe { CreateEventW(ptr::null_mut(), 0, 0, cs_name_event_want.as_ptr()) };
                 }
             }
-            // println!("h_want_frame_event: {:?}", self.h_want_frame_event);
-            if self.h_want_frame_event.is_null() {
-                return false;
-            }
+            self.check_opened("want event", self.h_want_frame_event)?;
         }
         if self.h_send_frame_event.is_null() {
             match for_receiving {
                 true => {
                     self.h_send_frame_event = unsafe {
-                        CreateEventA(
-                            std::ptr::null_mut(),
-                            0,
-                            0,
-                            cs_name_event_sent.as_ptr() as *const i8,
-                        )
+                        CreateEventW(std::ptr::null_mut(), 0, 0, cs_name_event_sent.as_ptr())
                     };
                 }
                 false => {
-                    self.h_send_frame_event = unsafe {
-                        OpenEventA(
-                            EVENT_MODIFY_STATE,
-                            0,
-                            cs_name_event_sent.as_ptr() as *const i8,
-                        )
-                    };
+                    self.h_send_frame_event =
+                        unsafe { OpenEventW(EVENT_MODIFY_STATE, 0, cs_name_event_sent.as_ptr()) };
                 }
             }
-            // println!("h_send_frame_event: {:?}", self.h_send_frame_event);
-            if self.h_send_frame_event.is_null() {
-                return false;
-            }
+            self.check_opened("sent event", self.h_send_frame_event)?;
         }
         if self.h_shared_file.is_null() {
             match for_receiving {
@@ -269,40 +441,30 @@ impl SharedImageMemory {
                     let header_size = std::mem::size_of::<SharedMemHeader>();
                     let mapping_size = header_size + MAX_SHARED_IMAGE_SIZE;
                     self.h_shared_file = unsafe {
-                        CreateFileMappingA(
+                        CreateFileMappingW(
                             INVALID_HANDLE_VALUE,
                             std::ptr::null_mut(),
                             PAGE_READWRITE,
                             0,
                             mapping_size as u32,
-                            cs_name_shared_data.as_ptr() as *const i8,
+                            cs_name_shared_data.as_ptr(),
                         )
                     };
                 }
                 false => {
                     self.h_shared_file = unsafe {
-                        OpenFileMappingA(
-                            FILE_MAP_WRITE,
-                            0,
-                            cs_name_shared_data.as_ptr() as *const i8,
-                        )
+                        OpenFileMappingW(FILE_MAP_WRITE, 0, cs_name_shared_data.as_ptr())
                     };
                 }
             }
-            // println!("h_shared_file: {:?}", self.h_shared_file);
 
-            if self.h_shared_file.is_null() {
-                return false;
-            }
+            self.check_opened("data mapping", self.h_shared_file)?;
         }
         self.m_p_shared_buf = unsafe {
             MapViewOfFile(self.h_shared_file, FILE_MAP_WRITE, 0, 0, 0 as usize)
                 as *mut SharedMemHeader
         };
-        // println!("m_p_shared_buf: {:?}", self.m_p_shared_buf);
-        if self.m_p_shared_buf.is_null() {
-            return false;
-        }
+        self.check_opened("data view", self.m_p_shared_buf as HANDLE)?;
 
         if for_receiving
             && unsafe { self.m_p_shared_buf.as_ref().unwrap().max_size }
@@ -313,7 +475,23 @@ impl SharedImageMemory {
             };
         }
 
-        true
+        info!(cap_num = self.cap_num, for_receiving; "opened UnityCapture shared memory");
+        Ok(())
+    }
+
+    /// Logs the result of one open step; a null handle means the receiver
+    /// is not (yet) up.
+    fn check_opened(&self, object: &str, handle: HANDLE) -> Result<(), Error> {
+        if handle.is_null() {
+            let os_error = std::io::Error::last_os_error();
+            trace!(
+                cap_num = self.cap_num, object, os_error:%;
+                "failed to open UnityCapture object"
+            );
+            return Err(Error::UnityCaptureNotInitialized);
+        }
+        trace!(cap_num = self.cap_num, object, handle:?; "opened UnityCapture object");
+        Ok(())
     }
 
     pub fn send(
@@ -327,11 +505,33 @@ impl SharedImageMemory {
         mirrormode: i32,
         timeout: i32,
         buffer: Vec<u8>,
+    ) -> Result<(), Error> {
+        self.send_with_metadata(
+            width, height, stride, data_size, e_format, resizemode, mirrormode, timeout, buffer,
+            None,
+        )
+    }
+
+    /// Like [`send`](Self::send), writing `metadata` into its block once the
+    /// frame is copied, before the mutex is released.
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn send_with_metadata(
+        &mut self,
+        width: i32,
+        height: i32,
+        stride: i32,
+        data_size: u32,
+        e_format: i32,
+        resizemode: i32,
+        mirrormode: i32,
+        timeout: i32,
+        buffer: Vec<u8>,
+        metadata: Option<(&mut MetadataBlock, &FrameMetadata)>,
     ) -> Result<(), Error> {
         if unsafe { self.m_p_shared_buf.as_mut().unwrap().max_size } < data_size {
             return Err(Error::SendresToolarge);
         }
-        unsafe { WaitForSingleObject(self.h_mutex, INFINITE) };
+        let cs = self.lock()?;
         unsafe { self.m_p_shared_buf.as_mut().unwrap().width = width };
         unsafe { self.m_p_shared_buf.as_mut().unwrap().height = height };
         unsafe { self.m_p_shared_buf.as_mut().unwrap().stride = stride };
@@ -346,9 +546,14 @@ impl SharedImageMemory {
                 data_size as usize,
             )
         }
+        let published = match metadata {
+            Some((block, metadata)) => block.write(metadata),
+            None => Ok(()),
+        };
 
-        unsafe { ReleaseMutex(self.h_mutex) };
+        drop(cs);
         unsafe { SetEvent(self.h_send_frame_event) };
+        published?;
         let ret = unsafe { WaitForSingleObject(self.h_want_frame_event, 0) != WAIT_OBJECT_0 };
 
         if ret {
@@ -359,6 +564,128 @@ impl SharedImageMemory {
     }
 
     pub fn send_is_ready(&mut self) -> bool {
+        self.open(false).is_ok()
+    }
+
+    /// Opens the receiver's shared objects if they are not open yet.
+    pub fn open_for_sending(&mut self) -> Result<(), Error> {
         self.open(false)
     }
 }
+
+impl Drop for SharedImageMemory {
+    fn drop(&mut self) {
+        self.close();
+    }
+}
+
+/// Maps a `WaitForSingleObject` result on the shared mutex to `Ok(())` if
+/// the mutex is now owned, or to the error to report and whether the wait
+/// took ownership anyway (an abandoned mutex) and it must be released.
+fn lock_result(wait: DWORD) -> Result<(), (Error, bool)> {
+    match wait {
+        WAIT_OBJECT_0 => Ok(()),
+        WAIT_ABANDONED => Err((Error::MutexAbandoned, true)),
+        WAIT_TIMEOUT => Err((Error::MutexTimeout, false)),
+        _ => Err((Error::UnityCaptureUnknownError, false)),
+    }
+}
+
+struct UnlockAtReturn {
+    m: HANDLE,
+}
+
+impl Drop for UnlockAtReturn {
+    fn drop(&mut self) {
+        unsafe {
+            ReleaseMutex(self.m);
+        }
+    }
+}
+
+#[test]
+fn test_lock_result() {
+    use winapi::um::winbase::WAIT_FAILED;
+
+    assert!(matches!(lock_result(WAIT_OBJECT_0), Ok(())));
+    assert!(matches!(
+        lock_result(WAIT_ABANDONED),
+        Err((Error::MutexAbandoned, true))
+    ));
+    assert!(matches!(
+        lock_result(WAIT_TIMEOUT),
+        Err((Error::MutexTimeout, false))
+    ));
+    assert!(matches!(
+        lock_result(WAIT_FAILED),
+        Err((Error::UnityCaptureUnknownError, false))
+    ));
+}
+
+#[test]
+fn test_lock_timeout_and_reopen() {
+    use std::sync::mpsc;
+
+    // stand in for a receiver on a capture number no filter uses
+    let cap_num = MAX_CAPNUM - 1;
+    let name = move |prefix: &str| object_name(prefix, cap_num).unwrap();
+    let receiver = unsafe {
+        [
+            CreateMutexW(ptr::null_mut(), 0, name("UnityCapture_Mutx").as_ptr()),
+            CreateEventW(ptr::null_mut(), 0, 0, name("UnityCapture_Sent").as_ptr()),
+            CreateFileMappingW(
+                INVALID_HANDLE_VALUE,
+                ptr::null_mut(),
+                PAGE_READWRITE,
+                0,
+                std::mem::size_of::<SharedMemHeader>() as u32,
+                name("UnityCapture_Data").as_ptr(),
+            ),
+        ]
+    };
+    assert!(receiver.iter().all(|handle| !handle.is_null()));
+
+    let mut shared = SharedImageMemory::new(cap_num);
+    shared.set_lock_timeout(Some(Duration::from_millis(50)));
+    shared.open_for_sending().unwrap();
+
+    // A second handle owned by another thread keeps the sender out; the
+    // thread exits still holding it on `abandon`.
+    let hold = |abandon: bool| {
+        let (held_tx, held_rx) = mpsc::channel();
+        let (release_tx, release_rx) = mpsc::channel::<()>();
+        let holder = std::thread::spawn(move || unsafe {
+            let mutex = OpenMutexW(SYNCHRONIZE, 0, name("UnityCapture_Mutx").as_ptr());
+            assert_eq!(WaitForSingleObject(mutex, INFINITE), WAIT_OBJECT_0);
+            held_tx.send(()).unwrap();
+            release_rx.recv().ok();
+            if !abandon {
+                ReleaseMutex(mutex);
+            }
+            CloseHandle(mutex);
+        });
+        held_rx.recv().unwrap();
+        (holder, release_tx)
+    };
+
+    let (holder, release) = hold(false);
+    assert!(matches!(shared.lock(), Err(Error::MutexTimeout)));
+    assert!(shared.h_mutex.is_null());
+    drop(release);
+    holder.join().unwrap();
+    shared.open_for_sending().unwrap();
+    assert!(shared.lock().is_ok());
+
+    let (holder, release) = hold(true);
+    drop(release);
+    holder.join().unwrap();
+    assert!(matches!(shared.lock(), Err(Error::MutexAbandoned)));
+    assert!(shared.h_mutex.is_null());
+    shared.open_for_sending().unwrap();
+    assert!(shared.lock().is_ok());
+
+    drop(shared);
+    for handle in receiver {
+        unsafe { CloseHandle(handle) };
+    }
+}
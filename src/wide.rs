@@ -0,0 +1,46 @@
+use crate::Error;
+
+/// A NUL-terminated UTF-16 string for the `W` Windows APIs, used for kernel
+/// object names.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WideString(Vec<u16>);
+
+impl WideString {
+    /// Encodes `s`, rejecting interior NULs, which would silently truncate
+    /// the name Windows sees.
+    pub fn new(s: &str) -> Result<Self, Error> {
+        if s.contains('\0') {
+            return Err(Error::InvalidArgument);
+        }
+        Ok(Self(s.encode_utf16().chain(Some(0)).collect()))
+    }
+
+    pub fn as_ptr(&self) -> *const u16 {
+        self.0.as_ptr()
+    }
+
+    /// The code units without the terminator.
+    pub fn as_slice(&self) -> &[u16] {
+        &self.0[..self.0.len() - 1]
+    }
+
+    /// Decodes a possibly NUL-terminated UTF-16 buffer, stopping at the
+    /// first NUL.
+    pub fn decode(wide: &[u16]) -> String {
+        let len = wide.iter().position(|&c| c == 0).unwrap_or(wide.len());
+        String::from_utf16_lossy(&wide[..len])
+    }
+}
+
+#[test]
+fn test_wide_string() {
+    let name = WideString::new("OBSVirtualCamVideo").unwrap();
+    assert_eq!(name.as_slice().len(), 18);
+    assert_eq!(unsafe { *name.as_ptr().add(18) }, 0);
+    assert_eq!(WideString::decode(name.as_slice()), "OBSVirtualCamVideo");
+
+    let name = WideString::new("Kamera \u{1F4F7}").unwrap();
+    assert_eq!(name.as_slice().len(), 9);
+    assert_eq!(WideString::decode(&name.0), "Kamera \u{1F4F7}");
+    assert!(WideString::new("a\0b").is_err());
+}
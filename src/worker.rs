@@ -0,0 +1,239 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::{self, JoinHandle};
+
+use crate::pacer::now_100ns;
+use crate::stats::CameraStats;
+use crate::{Camera, Error, Frame};
+
+/// What [`CameraWorker::send`] does when the queue is full.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DropPolicy {
+    /// Discard the oldest queued frame to make room.
+    DropOldest,
+    /// Discard the submitted frame and return `Error::QueueFull`.
+    DropNewest,
+    /// Wait until the worker has taken a frame off the queue.
+    Block,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct WorkerStats {
+    /// Frames accepted by `send`.
+    pub submitted: u64,
+    /// Frames the backend accepted.
+    pub sent: u64,
+    /// Frames the backend reported as skipped by the consumer.
+    pub skipped: u64,
+    /// Frames discarded because the queue was full.
+    pub dropped: u64,
+    /// Frames that failed with any other error.
+    pub errors: u64,
+    /// Frames currently waiting in the queue.
+    pub queued: usize,
+}
+
+struct State {
+    queue: VecDeque<Frame>,
+    stats: WorkerStats,
+    camera_stats: CameraStats,
+    closed: bool,
+}
+
+struct Shared {
+    state: Mutex<State>,
+    not_empty: Condvar,
+    not_full: Condvar,
+}
+
+impl Shared {
+    /// Stops accepting frames and wakes every waiter. Also runs when the
+    /// worker thread panics, so blocked senders see `Error::WorkerStopped`.
+    fn close(&self) {
+        self.state.lock().unwrap_or_else(|e| e.into_inner()).closed = true;
+        self.not_empty.notify_all();
+        self.not_full.notify_all();
+    }
+}
+
+struct CloseOnExit(Arc<Shared>);
+
+impl Drop for CloseOnExit {
+    fn drop(&mut self) {
+        self.0.close();
+    }
+}
+
+/// Runs a [`Camera`] on a dedicated thread behind a bounded frame queue, so
+/// the submitting thread never waits on the consumer's mutex.
+pub struct CameraWorker {
+    shared: Arc<Shared>,
+    capacity: usize,
+    policy: DropPolicy,
+    thread: Option<JoinHandle<Camera>>,
+}
+
+impl CameraWorker {
+    pub fn spawn(camera: Camera, capacity: usize, policy: DropPolicy) -> Self {
+        let capacity = capacity.max(1);
+        let shared = Arc::new(Shared {
+            state: Mutex::new(State {
+                queue: VecDeque::with_capacity(capacity),
+                stats: WorkerStats::default(),
+                camera_stats: CameraStats::default(),
+                closed: false,
+            }),
+            not_empty: Condvar::new(),
+            not_full: Condvar::new(),
+        });
+        let worker_shared = shared.clone();
+        let thread = thread::Builder::new()
+            .name("virtualcam-worker".to_owned())
+            .spawn(move || run(camera, worker_shared))
+            .expect("failed to spawn camera worker thread");
+        Self {
+            shared,
+            capacity,
+            policy,
+            thread: Some(thread),
+        }
+    }
+
+    /// Queues a frame for the worker. Frames without a timestamp are stamped
+    /// here, so pacing reflects submission time rather than queueing delay.
+    pub fn send<F: Into<Frame>>(&self, frame: F) -> Result<(), Error> {
+        let mut frame = frame.into();
+        frame.timestamp.get_or_insert_with(now_100ns);
+
+        let mut state = self.shared.state.lock().unwrap();
+        if state.closed {
+            return Err(Error::WorkerStopped);
+        }
+        while state.queue.len() >= self.capacity {
+            match self.policy {
+                DropPolicy::DropOldest => {
+                    state.queue.pop_front();
+                    state.stats.dropped += 1;
+                }
+                DropPolicy::DropNewest => {
+                    state.stats.dropped += 1;
+                    return Err(Error::QueueFull);
+                }
+                DropPolicy::Block => {
+                    state = self.shared.not_full.wait(state).unwrap();
+                    if state.closed {
+                        return Err(Error::WorkerStopped);
+                    }
+                }
+            }
+        }
+        state.queue.push_back(frame);
+        state.stats.submitted += 1;
+        drop(state);
+        self.shared.not_empty.notify_one();
+        Ok(())
+    }
+
+    pub fn stats(&self) -> WorkerStats {
+        let state = self.shared.state.lock().unwrap();
+        WorkerStats {
+            queued: state.queue.len(),
+            ..state.stats
+        }
+    }
+
+    /// The camera's own stats, as of the last frame the worker sent.
+    pub fn camera_stats(&self) -> CameraStats {
+        self.shared.state.lock().unwrap().camera_stats
+    }
+
+    /// Sends the frames still queued, stops the worker and hands the camera
+    /// back. A worker thread that panicked has lost the camera and returns
+    /// `Error::WorkerStopped`.
+    pub fn close(mut self) -> Result<Camera, Error> {
+        self.stop().ok_or(Error::WorkerStopped)
+    }
+
+    fn stop(&mut self) -> Option<Camera> {
+        self.shared.close();
+        self.thread.take().and_then(|thread| thread.join().ok())
+    }
+}
+
+impl Drop for CameraWorker {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+fn run(mut camera: Camera, shared: Arc<Shared>) -> Camera {
+    let _close = CloseOnExit(shared.clone());
+    loop {
+        let frame = {
+            let mut state = shared.state.lock().unwrap();
+            loop {
+                if let Some(frame) = state.queue.pop_front() {
+                    break frame;
+                }
+                if state.closed {
+                    return camera;
+                }
+                state = shared.not_empty.wait(state).unwrap();
+            }
+        };
+        shared.not_full.notify_one();
+
+        let result = camera.send(frame);
+        let mut state = shared.state.lock().unwrap();
+        state.camera_stats = camera.stats();
+        match result {
+            Ok(()) => state.stats.sent += 1,
+            Err(Error::SendresWarnFrameskip) => state.stats.skipped += 1,
+            Err(_) => state.stats.errors += 1,
+        }
+    }
+}
+
+#[test]
+fn test_worker_with_mock_backend() {
+    use crate::format::PixelFormat;
+    use crate::mock::{MockAction, MockBackend};
+    use crate::Backend;
+
+    let mock = MockBackend::new(2, 1, PixelFormat::Rgba)
+        .on_send(1, MockAction::Skip)
+        .on_send(2, MockAction::Fail(Error::MutexTimeout));
+    let camera = Camera::from_backend(Backend::Mock(mock.clone()));
+    let worker = CameraWorker::spawn(camera, 4, DropPolicy::Block);
+    for i in 0..4u64 {
+        worker
+            .send(Frame::with_timestamp(vec![i as u8; 8], i))
+            .unwrap();
+    }
+    let stats = {
+        let camera = worker.close().unwrap();
+        assert_eq!(camera.stats().sent, 2);
+        assert_eq!(camera.width(), 2);
+        mock.frames()
+    };
+    assert_eq!(
+        stats.iter().map(|f| f.timestamp).collect::<Vec<_>>(),
+        [0, 1, 3]
+    );
+}
+
+#[test]
+fn test_worker_panic_stops_senders() {
+    use crate::format::PixelFormat;
+    use crate::mock::{MockAction, MockBackend};
+    use crate::Backend;
+
+    let mock = MockBackend::new(2, 1, PixelFormat::Rgba).on_send(0, MockAction::Panic);
+    let camera = Camera::from_backend(Backend::Mock(mock.clone()));
+    let worker = CameraWorker::spawn(camera, 1, DropPolicy::Block);
+    // blocks on the full queue until the panic closes it
+    let stopped = (0..4).any(|_| matches!(worker.send(vec![0; 8]), Err(Error::WorkerStopped)));
+    assert!(stopped);
+    assert_eq!(mock.sends(), 1);
+    assert!(matches!(worker.close(), Err(Error::WorkerStopped)));
+}
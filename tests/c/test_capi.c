@@ -0,0 +1,74 @@
+/* Exercises the C API without a virtual camera driver installed. Results are
+ * stored before they are asserted on so NDEBUG builds still make every call. */
+#include <assert.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+
+#include "vcam.h"
+
+int main(void) {
+    VcamCamera *camera = NULL;
+    enum VcamStatus status;
+    size_t len;
+    const char *text;
+
+    /* unknown backends are rejected and leave *out null */
+    status = vcam_open("no-such-backend", NULL, 64, 48, &camera);
+    assert(status == VCAM_STATUS_INVALID_ARGUMENT);
+    assert(camera == NULL);
+    text = vcam_last_error();
+    assert(strlen(text) > 0);
+
+    /* null arguments are errors, not crashes */
+    status = vcam_open(NULL, NULL, 64, 48, NULL);
+    assert(status == VCAM_STATUS_INVALID_ARGUMENT);
+    status = vcam_send(NULL, NULL, 0, 0);
+    assert(status == VCAM_STATUS_INVALID_ARGUMENT);
+    status = vcam_set_option(NULL, "fps", "30");
+    assert(status == VCAM_STATUS_INVALID_ARGUMENT);
+    vcam_close(NULL);
+
+    /* sizing call, then the real one */
+    size_t needed = vcam_list_devices(NULL, 0);
+    assert(needed >= 1);
+    char *devices = malloc(needed);
+    len = vcam_list_devices(devices, needed);
+    assert(len == needed);
+    assert(strlen(devices) == needed - 1);
+    free(devices);
+
+    /* the mock backend accepts frames of exactly the camera's RGBA size */
+    status = vcam_open("mock", NULL, 4, 2, &camera);
+    assert(status == VCAM_STATUS_OK);
+    assert(camera != NULL);
+    unsigned char frame[4 * 2 * 4] = {0};
+    status = vcam_send(camera, frame, sizeof frame, 0);
+    assert(status == VCAM_STATUS_OK);
+    status = vcam_send(camera, frame, sizeof frame, 1000);
+    assert(status == VCAM_STATUS_OK);
+    status = vcam_send(camera, frame, 3, 0);
+    assert(status == VCAM_STATUS_INVALID_FRAME_SIZE);
+    text = vcam_last_error();
+    assert(strcmp(text, "InvalidFrameSize") == 0);
+    status = vcam_set_option(camera, "fps", "0");
+    assert(status == VCAM_STATUS_OK);
+    status = vcam_set_option(camera, "lock_timeout_ms", "-1");
+    assert(status == VCAM_STATUS_OK);
+    status = vcam_set_option(camera, "no-such-option", "1");
+    assert(status == VCAM_STATUS_INVALID_ARGUMENT);
+    vcam_close(camera);
+
+    text = vcam_status_string(VCAM_STATUS_OK);
+    assert(strcmp(text, "ok") == 0);
+    text = vcam_status_string(VCAM_STATUS_INVALID_FRAME_SIZE);
+    assert(strcmp(text, "frame is not the camera's size") == 0);
+    text = vcam_status_string(-1);
+    assert(strcmp(text, "unknown error") == 0);
+
+    (void)status;
+    (void)len;
+    (void)text;
+    printf("test_capi: ok\n");
+    return 0;
+}
@@ -0,0 +1,21 @@
+import numpy as np
+import pytest
+
+import virtualcam_rs
+
+
+def test_mock_camera_send():
+    with virtualcam_rs.Camera(width=4, height=2, fps=30, backend="mock") as cam:
+        cam.send(np.zeros((cam.height, cam.width, 3), np.uint8))
+        cam.send(np.zeros((cam.height, cam.width, 4), np.uint8))
+        # other sizes are fitted to the camera
+        cam.send(np.zeros((3, 3, 3), np.uint8))
+        with pytest.raises(ValueError):
+            cam.send(np.zeros((2, 4, 2), np.uint8))
+    with pytest.raises(RuntimeError):
+        cam.send(np.zeros((2, 4, 3), np.uint8))
+
+
+def test_invalid_fps():
+    with pytest.raises(ValueError):
+        virtualcam_rs.Camera(width=4, height=2, fps=1e-300, backend="mock")